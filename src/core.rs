@@ -1,16 +1,85 @@
+pub mod clone;
+pub mod create;
+pub mod difficulty;
+pub mod eoa;
+pub mod expression;
+pub mod hooks;
+pub mod keccak;
+pub mod kernel;
+pub mod keyspace;
+pub mod lanes;
+pub mod pattern;
+pub mod safe;
+pub mod salt;
+pub mod score;
+pub mod self_test;
+pub mod zksync;
+
 use alloy_primitives::{hex, Address, Keccak256};
-use rand::{Rng, SeedableRng};
-use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 
+use expression::Expression;
+use keyspace::{Keyspace, KeyspaceIndex};
+use lanes::MessageLanes;
+use pattern::Pattern;
+use safe::{SafeDeployment, DEFAULT_SAFE_SALT_TEMPLATE};
+use salt::{SaltTemplate, DEFAULT_SALT_TEMPLATE};
+use score::Ranking;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MinerConfig {
     pub factory: [u8; 20],
-    pub caller: [u8; 20],
+    pub caller: Option<[u8; 20]>,
     pub codehash: [u8; 32],
     pub worksize: u32,
-    pub pattern: Vec<u8>,
-    pub pattern_len: usize,
+    pub pattern: Pattern,
+    /// Nibbles that have to appear anywhere in the address, in the `contains` match mode.
+    pub contains: Option<Vec<u8>>,
+    /// Pattern expression the address has to match, in the `expression` match mode.
+    pub expression: Option<Expression>,
+    pub ranking: Ranking,
+    /// Score an address matching the pattern needs to be reported, see [`Ranking::score`].
+    pub min_score: u32,
+    pub salt: SaltTemplate,
+    pub derivation: Derivation,
+}
+
+impl MinerConfig {
+    /// The address constraints for display, with `*` around nibbles that may appear
+    /// anywhere in the `contains` match mode.
+    pub fn pattern_label(&self) -> String {
+        if let Some(expression) = &self.expression {
+            return match self.pattern == Pattern::any() {
+                true => expression.source.clone(),
+                false => format!("{} & {}", self.pattern, expression.source),
+            };
+        }
+        let Some(nibbles) = &self.contains else {
+            return self.pattern.to_string();
+        };
+        let contains: String = nibbles
+            .iter()
+            .map(|&nibble| char::from_digit(nibble as u32, 16).unwrap())
+            .collect();
+        if self.pattern == Pattern::any() {
+            format!("*{}*", contains)
+        } else {
+            format!("{} & *{}*", self.pattern, contains)
+        }
+    }
+}
+
+/// How the mined salt turns into the CREATE2 salt seen by the factory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Derivation {
+    /// The salt is passed to the factory as is.
+    Create2,
+    /// Safe `createProxyWithNonce`: the mined salt is the `saltNonce`, which the proxy
+    /// factory hashes together with the initializer hash into the CREATE2 salt.
+    Safe { initializer_hash: [u8; 32] },
+    /// zkSync Era CREATE2, which hashes a prefix, the padded factory, the salt, the
+    /// bytecode hash and the constructor input hash.
+    ZkSync { input_hash: [u8; 32] },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,23 +87,137 @@ pub struct FoundResult {
     pub salt: String,
     pub address: String,
     pub pattern: String,
+    /// Score of the address in a ranking mode, e.g. the zero bytes and gas saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<String>,
+    /// Nibble offset of the pattern in the address, in the `contains` match mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RawConfig {
     pub factory: String,
-    pub caller: String,
-    pub codehash: String,
+    #[serde(default)]
+    pub caller: Option<String>,
+    #[serde(default)]
+    pub codehash: Option<String>,
     pub worksize: u32,
+    /// Hex prefix, may be empty when `hooks` or `ranking` is set.
+    #[serde(default)]
     pub pattern: String,
+    /// `prefix` (default), `contains` to find the pattern anywhere in the address or
+    /// `expression` for a pattern expression, see [`Expression`].
+    #[serde(default)]
+    pub match_mode: Option<String>,
+    /// Uniswap v4 hook callbacks the address has to encode, e.g. `beforeSwap,afterSwap`.
+    #[serde(default)]
+    pub hooks: Option<String>,
+    /// `none` (default), `zero-bytes`, `repeated-nibbles` or `repeated-bytes`.
+    #[serde(default)]
+    pub ranking: Option<String>,
+    /// Minimum score of a reported address, e.g. the number of zero bytes.
+    #[serde(default)]
+    pub min_score: Option<u32>,
+    #[serde(default)]
+    pub salt: Option<String>,
+    /// `create2` (default), `clone` or `safe`.
+    #[serde(default)]
+    pub derivation: Option<String>,
+    #[serde(default)]
+    pub singleton: Option<String>,
+    #[serde(default)]
+    pub initializer: Option<String>,
+    #[serde(default)]
+    pub proxy_code: Option<String>,
+    #[serde(default)]
+    pub implementation: Option<String>,
+    #[serde(default)]
+    pub clone_args: Option<String>,
+    /// `ethereum` (default) or `zksync`.
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub constructor_input: Option<String>,
 }
 
 pub fn parse_config(raw: RawConfig) -> Result<MinerConfig, String> {
     let factory = parse_fixed_hex::<20>(&raw.factory, "factory")?;
-    let caller = parse_fixed_hex::<20>(&raw.caller, "caller")?;
-    let codehash = parse_fixed_hex::<32>(&raw.codehash, "codehash")?;
-    let pattern = parse_pattern(&raw.pattern)?;
-    let pattern_len = pattern.len();
+    let caller = match raw.caller.as_deref().map(str::trim) {
+        Some(caller) if !caller.is_empty() => Some(parse_fixed_hex::<20>(caller, "caller")?),
+        _ => None,
+    };
+    let ranking = Ranking::parse(raw.ranking.as_deref())?;
+    let min_score = ranking.min_score(raw.min_score.unwrap_or(0));
+
+    let (contains, expression) = match raw.match_mode.as_deref() {
+        None | Some("prefix") => (None, None),
+        Some("contains") => (Some(pattern::parse_nibbles(&raw.pattern)?), None),
+        Some("expression") => (None, Some(Expression::parse(&raw.pattern)?)),
+        Some(other) => return Err(format!("Unknown match mode: '{}'.", other)),
+    };
+
+    // hook flags and rankings are targets on their own, so they do not need a prefix
+    let targets_without_prefix = raw.hooks.is_some() || ranking != Ranking::None;
+    let prefix_mode = contains.is_none() && expression.is_none();
+    let mut pattern = if !prefix_mode || (raw.pattern.trim().is_empty() && targets_without_prefix) {
+        Pattern::any()
+    } else {
+        Pattern::prefix(&parse_pattern(&raw.pattern)?)
+    };
+    if let Some(hooks) = raw.hooks.as_deref() {
+        pattern = pattern
+            .and(&hooks::hook_pattern(hooks::parse_hook_flags(hooks)?))
+            .ok_or("Pattern conflicts with the hook flags.")?;
+    }
+
+    let (codehash, derivation, default_salt) = match raw.derivation.as_deref() {
+        None | Some("create2") => {
+            let codehash = raw.codehash.as_deref().ok_or("Codehash is required.")?;
+            let codehash = parse_fixed_hex::<32>(codehash, "codehash")?;
+            (codehash, Derivation::Create2, DEFAULT_SALT_TEMPLATE)
+        }
+        Some("clone") => {
+            let implementation = raw
+                .implementation
+                .as_deref()
+                .ok_or("Clone derivation requires an implementation.")?;
+            let implementation = parse_fixed_hex::<20>(implementation, "implementation")?;
+            let codehash = clone::clone_codehash(&implementation, raw.clone_args.as_deref())?;
+            (codehash, Derivation::Create2, DEFAULT_SALT_TEMPLATE)
+        }
+        Some("safe") => {
+            let (Some(singleton), Some(initializer), Some(proxy_code)) =
+                (&raw.singleton, &raw.initializer, &raw.proxy_code)
+            else {
+                return Err(
+                    "Safe derivation requires singleton, initializer and proxy code.".to_string(),
+                );
+            };
+            // the init code hash follows from the proxy code, a configured codehash is unused
+            let safe = SafeDeployment::parse(singleton, initializer, proxy_code)?;
+            let derivation = Derivation::Safe {
+                initializer_hash: safe.initializer_hash(),
+            };
+            (safe.codehash(), derivation, DEFAULT_SAFE_SALT_TEMPLATE)
+        }
+        Some(other) => return Err(format!("Unknown derivation: '{}'.", other)),
+    };
+
+    let derivation = match (raw.chain.as_deref(), derivation) {
+        (None | Some("ethereum"), derivation) => derivation,
+        (Some("zksync"), Derivation::Create2) if raw.derivation.as_deref() != Some("clone") => {
+            // the codehash is the zkSync bytecode hash of the contract
+            let input_hash = zksync::constructor_input_hash(raw.constructor_input.as_deref())?;
+            Derivation::ZkSync { input_hash }
+        }
+        (Some("zksync"), _) => {
+            return Err("zkSync chain only supports the create2 derivation.".to_string())
+        }
+        (Some(other), _) => return Err(format!("Unknown chain: '{}'.", other)),
+    };
+
+    let salt = SaltTemplate::parse(raw.salt.as_deref().unwrap_or(default_salt), caller.as_ref())?;
 
     Ok(MinerConfig {
         factory,
@@ -42,11 +225,16 @@ pub fn parse_config(raw: RawConfig) -> Result<MinerConfig, String> {
         codehash,
         worksize: raw.worksize,
         pattern,
-        pattern_len,
+        contains,
+        expression,
+        ranking,
+        min_score,
+        salt,
+        derivation,
     })
 }
 
-fn parse_pattern(input: &str) -> Result<Vec<u8>, String> {
+pub fn parse_pattern(input: &str) -> Result<Vec<u8>, String> {
     let pattern_str = strip_0x(input);
     if pattern_str.is_empty() {
         return Err("Pattern cannot be empty.".to_string());
@@ -82,59 +270,122 @@ fn parse_fixed_hex<const N: usize>(input: &str, name: &str) -> Result<[u8; N], S
 }
 
 fn strip_0x(input: &str) -> &str {
-    input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")).unwrap_or(input)
+    input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .unwrap_or(input)
 }
 
-pub fn run_batch(
-    config: &MinerConfig,
-    seed: u64,
-    worker_id: u32,
-    batch_size: u32,
-) -> (Vec<FoundResult>, u32) {
-    let mut rng = ChaCha8Rng::seed_from_u64(seed ^ (worker_id as u64));
-    let salt: [u8; 4] = rng.gen();
-    let start_nonce: u64 = rng.gen();
+/// Address of the contract deployed through `config` with the given mined salt.
+pub fn compute_address(config: &MinerConfig, salt: &[u8; 32]) -> Address {
+    let salt = match &config.derivation {
+        Derivation::Create2 | Derivation::ZkSync { .. } => *salt,
+        Derivation::Safe { initializer_hash } => safe::create2_salt(initializer_hash, salt),
+    };
 
-    let mut found = Vec::new();
+    let mut hash = Keccak256::new();
 
-    for i in 0..batch_size {
-        let nonce = start_nonce.wrapping_add(i as u64).to_le_bytes();
+    if let Derivation::ZkSync { input_hash } = &config.derivation {
+        hash.update(zksync::message(
+            &config.factory,
+            &salt,
+            &config.codehash,
+            input_hash,
+        ));
+    } else {
         let mut message = [0u8; 85];
         message[0] = 0xff;
         message[1..21].copy_from_slice(&config.factory);
-        message[21..41].copy_from_slice(&config.caller);
-        message[41..45].copy_from_slice(&salt);
-        message[45..53].copy_from_slice(&nonce);
+        message[21..53].copy_from_slice(&salt);
         message[53..].copy_from_slice(&config.codehash);
+        hash.update(message);
+    }
+
+    let mut res = [0u8; 32];
+    hash.finalize_into(&mut res);
+
+    Address::from_slice(&res[12..])
+}
+
+/// Whether `address` starts with `pattern`.
+pub fn matches_pattern(pattern: &[u8], address: &Address) -> bool {
+    address.starts_with(pattern)
+}
 
-        let mut hash = Keccak256::new();
-        hash.update(&message);
-        let mut res = [0u8; 32];
-        hash.finalize_into(&mut res);
+/// Search batch `batch` of worker `worker` in `keyspace`, returning the matches and
+/// the number of salts tried, which is zero once the keyspace is exhausted.
+pub fn run_batch(
+    config: &MinerConfig,
+    keyspace: &Keyspace,
+    worker: u32,
+    batch: u64,
+) -> (Vec<FoundResult>, u32) {
+    let index = KeyspaceIndex {
+        worker,
+        batch,
+        offset: 0,
+    };
+    let Some(position) = keyspace.position(index) else {
+        return (Vec::new(), 0);
+    };
+    let random = keyspace.random(position.prefix);
+    let start_nonce = position.nonce;
+    let batch_size = keyspace.batch_size;
 
-        let address = <&Address>::try_from(&res[12..]).unwrap();
+    let lanes = MessageLanes::new(config, &random);
+    let mut found = Vec::new();
+    let mut min_score = config.min_score;
 
-        let mut matches = true;
-        for idx in 0..config.pattern_len {
-            if address[idx] != config.pattern[idx] {
-                matches = false;
-                break;
-            }
+    let mut visit = |nonce: u64, address: &Address| {
+        if !config.pattern.matches(address) {
+            return;
         }
 
-        if matches {
-            let salt_hex = format!(
-                "0x{}{}{}",
-                hex::encode(config.caller),
-                hex::encode(salt),
-                hex::encode(nonce)
-            );
+        let position = match &config.contains {
+            Some(nibbles) => match pattern::find_nibbles(nibbles, address) {
+                Some(position) => Some(position),
+                None => return,
+            },
+            None => None,
+        };
+        let position = match &config.expression {
+            Some(expression) => match expression.find(address) {
+                Some(position) => Some(position),
+                None => return,
+            },
+            None => position,
+        };
 
-            found.push(FoundResult {
-                salt: salt_hex,
-                address: address.to_string(),
-                pattern: format!("0x{}", hex::encode(&config.pattern)),
-            });
+        let score = config.ranking.score(address);
+        if score < min_score {
+            return;
+        }
+        // in a ranking mode, only report improvements over the best so far
+        if config.ranking != Ranking::None {
+            min_score = score + 1;
+        }
+
+        let salt = config.salt.fill(&random, nonce);
+        found.push(FoundResult {
+            salt: format!("0x{}", hex::encode(salt)),
+            address: address.to_string(),
+            pattern: config.pattern_label(),
+            score: config.ranking.describe(address),
+            position,
+        });
+    };
+
+    // hash 8 salts side by side, one AVX-512, two AVX2 or four WASM SIMD registers
+    // per lane
+    for offset in (0..batch_size).step_by(8) {
+        let nonce = start_nonce.wrapping_add(offset as u64);
+        let addresses = lanes.addresses::<8>(nonce);
+        for (j, address) in addresses
+            .iter()
+            .take((batch_size - offset) as usize)
+            .enumerate()
+        {
+            visit(nonce.wrapping_add(j as u64), address);
         }
     }
 
@@ -144,6 +395,7 @@ pub fn run_batch(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_primitives::{address, hex, keccak256, B256, U256};
 
     #[test]
     fn parse_pattern_rejects_empty() {
@@ -157,6 +409,211 @@ mod tests {
         assert!(parse_pattern(&long).is_err());
     }
 
+    fn raw_config() -> RawConfig {
+        RawConfig {
+            factory: "0x0000000000000000000000000000000000000000".to_string(),
+            codehash: Some(format!("0x{}", hex::encode(keccak256([0u8])))),
+            worksize: 1,
+            pattern: "00".to_string(),
+            salt: Some("zero-caller".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compute_address_matches_eip1014_example() {
+        // example 0 from EIP-1014: zero deployer, zero salt, init code 0x00
+        let config = parse_config(raw_config()).unwrap();
+        assert_eq!(
+            compute_address(&config, &[0u8; 32]),
+            address!("4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38")
+        );
+    }
+
+    #[test]
+    fn compute_address_hashes_safe_salt_nonce() {
+        let mut raw = raw_config();
+        raw.factory = "0x4e1DCf7AD4e460CfD30791CCC4F9c8a4f820ec67".to_string();
+        raw.derivation = Some("safe".to_string());
+        raw.singleton = Some("0x41675C099F32341bf84BFc5382aF534df5C7461a".to_string());
+        raw.initializer = Some("0xb63e800d".to_string());
+        raw.proxy_code = Some("0x6080604052".to_string());
+        let config = parse_config(raw).unwrap();
+
+        let salt_nonce = U256::from(42).to_be_bytes::<32>();
+        let salt = keccak256([keccak256(hex!("b63e800d")).0, salt_nonce].concat());
+        let init_code = hex!(
+            "6080604052"
+            "00000000000000000000000041675C099F32341bf84BFc5382aF534df5C7461a"
+        );
+        let expected = address!("4e1DCf7AD4e460CfD30791CCC4F9c8a4f820ec67")
+            .create2(salt, keccak256(init_code));
+
+        assert_eq!(compute_address(&config, &salt_nonce), expected);
+    }
+
+    #[test]
+    fn parse_config_derives_clone_codehash() {
+        let mut raw = raw_config();
+        raw.codehash = None;
+        raw.derivation = Some("clone".to_string());
+        raw.implementation = Some("0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_string());
+        let config = parse_config(raw.clone()).unwrap();
+        assert_eq!(
+            config.codehash,
+            keccak256(clone::clone_init_code(&[0xbb; 20])).0
+        );
+
+        raw.clone_args = Some("0x010203".to_string());
+        let config = parse_config(raw).unwrap();
+        let init_code = clone::clone_init_code_with_args(&[0xbb; 20], &[1, 2, 3]).unwrap();
+        assert_eq!(config.codehash, keccak256(init_code).0);
+    }
+
+    #[test]
+    fn compute_address_uses_zksync_preimage() {
+        let mut raw = raw_config();
+        raw.factory = "0x0000000000000000000000000000000000008006".to_string();
+        raw.chain = Some("zksync".to_string());
+        raw.constructor_input = Some("0x1234".to_string());
+        let config = parse_config(raw).unwrap();
+
+        let salt = [7u8; 32];
+        let preimage = [
+            keccak256("zksyncCreate2").0,
+            B256::left_padding_from(&config.factory).0,
+            salt,
+            config.codehash,
+            keccak256(hex!("1234")).0,
+        ]
+        .concat();
+        let expected = Address::from_word(keccak256(preimage));
+
+        assert_eq!(compute_address(&config, &salt), expected);
+    }
+
+    #[test]
+    fn parse_config_rejects_zksync_clones() {
+        let mut raw = raw_config();
+        raw.chain = Some("zksync".to_string());
+        raw.derivation = Some("clone".to_string());
+        raw.implementation = Some("0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_string());
+        assert!(parse_config(raw).is_err());
+    }
+
+    #[test]
+    fn parse_config_requires_safe_parameters() {
+        let mut raw = raw_config();
+        raw.derivation = Some("safe".to_string());
+        assert!(parse_config(raw).is_err());
+    }
+
+    #[test]
+    fn parse_config_combines_hooks_with_prefix() {
+        let mut raw = raw_config();
+        raw.pattern = "".to_string();
+        raw.hooks = Some("beforeSwap,afterSwap".to_string());
+        let config = parse_config(raw.clone()).unwrap();
+        assert_eq!(config.pattern, hooks::hook_pattern(0xc0));
+
+        raw.pattern = "0xabcd".to_string();
+        let config = parse_config(raw.clone()).unwrap();
+        assert_eq!(config.pattern.constrained_bits(), 16 + 14);
+
+        raw.pattern = "00".repeat(20);
+        assert!(parse_config(raw).is_err());
+    }
+
+    #[test]
+    fn run_batch_enumerates_keyspace() {
+        let config = parse_config(raw_config()).unwrap();
+        let keyspace = Keyspace::new(&config, 7, 2, 1 << 12);
+        let salts = |worker, batch| -> Vec<String> {
+            let (found, _) = run_batch(&config, &keyspace, worker, batch);
+            found.into_iter().map(|result| result.salt).collect()
+        };
+
+        let first = salts(0, 0);
+        assert!(!first.is_empty());
+        assert_eq!(first, salts(0, 0));
+        for other in [salts(0, 1), salts(1, 0)] {
+            assert!(other.iter().all(|salt| !first.contains(salt)));
+        }
+    }
+
+    #[test]
+    fn run_batch_matches_hook_flags() {
+        let mut raw = raw_config();
+        raw.pattern = "".to_string();
+        raw.hooks = Some("afterInitialize".to_string());
+        let config = parse_config(raw).unwrap();
+
+        let (found, _) = run_batch(&config, &Keyspace::new(&config, 1, 1, 1 << 16), 0, 0);
+        assert!(!found.is_empty());
+        for result in found {
+            let address: Address = result.address.parse().unwrap();
+            assert_eq!(
+                u16::from_be_bytes([address[18], address[19]]) & 0x3fff,
+                1 << 12
+            );
+        }
+    }
+
+    #[test]
+    fn run_batch_reports_zero_byte_improvements() {
+        let mut raw = raw_config();
+        raw.pattern = "".to_string();
+        raw.ranking = Some("zero-bytes".to_string());
+        raw.min_score = Some(1);
+        let config = parse_config(raw).unwrap();
+        assert_eq!(config.pattern, Pattern::any());
+
+        let (found, _) = run_batch(&config, &Keyspace::new(&config, 1, 1, 1 << 12), 0, 0);
+        assert!(!found.is_empty());
+        let scores: Vec<u32> = found
+            .iter()
+            .map(|result| config.ranking.score(&result.address.parse().unwrap()))
+            .collect();
+        assert!(scores[0] >= config.min_score);
+        assert!(scores.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(found[0].score.as_ref().unwrap().contains("gas saved"));
+    }
+
+    #[test]
+    fn run_batch_finds_contained_nibbles() {
+        let mut raw = raw_config();
+        raw.pattern = "abc".to_string();
+        raw.match_mode = Some("contains".to_string());
+        let config = parse_config(raw).unwrap();
+        assert_eq!(config.pattern, Pattern::any());
+        assert_eq!(config.pattern_label(), "*abc*");
+
+        let (found, _) = run_batch(&config, &Keyspace::new(&config, 1, 1, 1 << 10), 0, 0);
+        assert!(!found.is_empty());
+        for result in found {
+            let position = result.position.unwrap();
+            assert_eq!(&result.address.to_lowercase()[2 + position..][..3], "abc");
+        }
+    }
+
+    #[test]
+    fn run_batch_evaluates_expressions() {
+        let mut raw = raw_config();
+        raw.pattern = r"^(.)\1[0-7]".to_string();
+        raw.match_mode = Some("expression".to_string());
+        let config = parse_config(raw).unwrap();
+        assert_eq!(config.pattern_label(), r"^(.)\1[0-7]");
+
+        let (found, _) = run_batch(&config, &Keyspace::new(&config, 1, 1, 1 << 10), 0, 0);
+        assert!(!found.is_empty());
+        for result in found {
+            let address = result.address.to_lowercase();
+            assert_eq!(address[2..3], address[3..4]);
+            assert!(address.as_bytes()[4] <= b'7');
+            assert_eq!(result.position, Some(0));
+        }
+    }
+
     #[test]
     fn parse_fixed_hex_enforces_length() {
         let res = parse_fixed_hex::<20>("0xdeadbeef", "factory");
@@ -0,0 +1,87 @@
+use alloy_primitives::{hex, keccak256};
+
+use super::strip_0x;
+
+/// Longest immutable args accepted by OpenZeppelin's `Clones`, keeping the runtime code
+/// within the contract size limit.
+pub const MAX_CLONE_ARGS_LEN: usize = 24531;
+
+/// The 55-byte EIP-1167 minimal proxy init code delegating to `implementation`, as
+/// deployed by `Clones.cloneDeterministic`.
+pub fn clone_init_code(implementation: &[u8; 20]) -> Vec<u8> {
+    [
+        &hex!("3d602d80600a3d3981f3363d3d373d3d3d363d73")[..],
+        implementation,
+        &hex!("5af43d82803e903d91602b57fd5bf3"),
+    ]
+    .concat()
+}
+
+/// Init code of a minimal proxy with `args` appended to its runtime code, as deployed by
+/// OpenZeppelin's `Clones.cloneDeterministicWithImmutableArgs`.
+pub fn clone_init_code_with_args(
+    implementation: &[u8; 20],
+    args: &[u8],
+) -> Result<Vec<u8>, String> {
+    if args.len() > MAX_CLONE_ARGS_LEN {
+        return Err(format!(
+            "Clone args are too long ({} bytes). Maximum is {} bytes.",
+            args.len(),
+            MAX_CLONE_ARGS_LEN
+        ));
+    }
+
+    // the runtime size is pushed with PUSH2, covering the 45-byte proxy and the args
+    let runtime_len = (args.len() + 45) as u16;
+
+    Ok([
+        &[0x61][..],
+        &runtime_len.to_be_bytes(),
+        &hex!("3d81600a3d39f3363d3d373d3d3d363d73"),
+        implementation,
+        &hex!("5af43d82803e903d91602b57fd5bf3"),
+        args,
+    ]
+    .concat())
+}
+
+/// Init code hash of the clone of `implementation`, with immutable `args` when given.
+pub fn clone_codehash(implementation: &[u8; 20], args: Option<&str>) -> Result<[u8; 32], String> {
+    let init_code = match args {
+        None => clone_init_code(implementation),
+        Some(args) => {
+            let args = hex::decode(strip_0x(args))
+                .map_err(|_| format!("Invalid hex string for clone args: '{}'.", args))?;
+            clone_init_code_with_args(implementation, &args)?
+        }
+    };
+    Ok(keccak256(init_code).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_init_code_layout() {
+        let implementation = [0xbb; 20];
+        let code = clone_init_code(&implementation);
+        assert_eq!(code.len(), 55);
+        assert_eq!(&code[20..40], &implementation);
+        // the creation prefix returns the 45 bytes of runtime code
+        assert_eq!(code[2], 45);
+    }
+
+    #[test]
+    fn clone_init_code_with_args_layout() {
+        let implementation = [0xbb; 20];
+        let code = clone_init_code_with_args(&implementation, &[1, 2, 3]).unwrap();
+        assert_eq!(code.len(), 55 + 3);
+        assert_eq!(&code[1..3], &[0, 48]);
+        assert_eq!(&code[20..40], &implementation);
+        assert_eq!(&code[55..], &[1, 2, 3]);
+
+        let too_long = vec![0; MAX_CLONE_ARGS_LEN + 1];
+        assert!(clone_init_code_with_args(&implementation, &too_long).is_err());
+    }
+}
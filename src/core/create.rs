@@ -0,0 +1,29 @@
+use alloy_primitives::Address;
+use std::ops::Range;
+
+use super::matches_pattern;
+
+/// Every nonce in `nonces` for which the contract deployed by `deployer` with plain
+/// CREATE, at `keccak256(rlp([deployer, nonce]))[12:]`, starts with `pattern`.
+pub fn scan_nonces(deployer: &Address, pattern: &[u8], nonces: Range<u64>) -> Vec<(u64, Address)> {
+    nonces
+        .map(|nonce| (nonce, deployer.create(nonce)))
+        .filter(|(_, address)| matches_pattern(pattern, address))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn scan_nonces_finds_deposit_contract() {
+        let deployer = address!("b20a608c624Ca5003905aA834De7156C68b2E1d0");
+        let found = scan_nonces(&deployer, &[0, 0, 0, 0], 0..100);
+        assert_eq!(
+            found,
+            vec![(0, address!("00000000219ab540356cBB839Cbe05303d7705Fa"))]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use super::MinerConfig;
+
+/// Percentiles reported by `salty estimate`, as the probability of having found
+/// at least one match after the corresponding number of attempts.
+pub const PERCENTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+/// Probability that a single attempt produces an address accepted by `config`.
+pub fn match_probability(config: &MinerConfig) -> f64 {
+    0.5f64.powi(config.pattern.constrained_bits() as i32)
+        * config.ranking.probability(config.min_score)
+        * config
+            .contains
+            .as_ref()
+            .map_or(1.0, |nibbles| contains_probability(nibbles.len()))
+        * config
+            .expression
+            .as_ref()
+            .map_or(1.0, |expression| expression.probability())
+}
+
+/// Probability that `len` given nibbles appear somewhere in a random address, treating
+/// the 41 - `len` positions as independent.
+pub fn contains_probability(len: usize) -> f64 {
+    let positions = 41 - len.min(40);
+    success_probability(0.0625f64.powi(len as i32), positions as f64)
+}
+
+/// Expected number of attempts until the first match (the mean of the geometric
+/// distribution), i.e. the "difficulty" shown by the web UI.
+pub fn expected_attempts(probability: f64) -> f64 {
+    1.0 / probability
+}
+
+/// Number of attempts after which a match has been found with the given
+/// `confidence` (0.5 gives the median).
+pub fn attempts_for_confidence(probability: f64, confidence: f64) -> f64 {
+    // Solve 1 - (1 - p)^n = c for n, using ln_1p to stay accurate for tiny p.
+    (-confidence).ln_1p() / (-probability).ln_1p()
+}
+
+/// Probability of having found at least one match after `attempts` attempts.
+pub fn success_probability(probability: f64, attempts: f64) -> f64 {
+    if attempts <= 0.0 {
+        return 0.0;
+    }
+    -(attempts * (-probability).ln_1p()).exp_m1()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_matches_ln2_approximation() {
+        let p = 0.5f64.powi(24);
+        let median = attempts_for_confidence(p, 0.5);
+        let approx = expected_attempts(p) * std::f64::consts::LN_2;
+        assert!((median - approx).abs() / approx < 1e-6);
+    }
+
+    #[test]
+    fn contains_probability_counts_positions() {
+        assert_eq!(contains_probability(40), 0.0625f64.powi(40));
+        let p = contains_probability(6);
+        assert!((p / (35.0 * 0.0625f64.powi(6)) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn success_probability_inverts_confidence() {
+        let p = 0.5f64.powi(40);
+        for confidence in PERCENTILES {
+            let n = attempts_for_confidence(p, confidence);
+            assert!((success_probability(p, n) - confidence).abs() < 1e-9);
+        }
+        assert_eq!(success_probability(p, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,162 @@
+use alloy_primitives::Address;
+use k256::{
+    elliptic_curve::{sec1::ToEncodedPoint, BatchNormalize},
+    ProjectivePoint, Scalar, SecretKey,
+};
+
+use super::matches_pattern;
+
+/// Number of consecutive public keys converted to affine coordinates at once, sharing a
+/// single field inversion.
+const NORMALIZE_BATCH: usize = 256;
+
+/// The address derived from an account that has to match the pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyTarget {
+    /// The address of the account itself.
+    Account,
+    /// The address of the contract the account deploys with plain CREATE at the given
+    /// nonce, e.g. 0 for its very first transaction.
+    Create(u64),
+}
+
+impl KeyTarget {
+    pub fn address(&self, account: &Address) -> Address {
+        match *self {
+            KeyTarget::Account => *account,
+            KeyTarget::Create(nonce) => account.create(nonce),
+        }
+    }
+}
+
+/// A private key whose target address matches the pattern.
+pub struct FoundKey {
+    pub secret: [u8; 32],
+    /// Address of the account controlled by the key.
+    pub address: Address,
+    /// The matching address, see [`KeyTarget`].
+    pub target: Address,
+}
+
+/// Address of the externally owned account controlled by `secret`.
+pub fn address_of(secret: &SecretKey) -> Address {
+    let point = secret.public_key().to_encoded_point(false);
+    Address::from_raw_public_key(&point.as_bytes()[1..])
+}
+
+/// Search `count` consecutive private keys starting at `start` for one whose `target`
+/// address starts with `pattern`. Returns the first match (if any) and the number of
+/// keys tried.
+///
+/// Stepping to the next key only takes a point addition instead of a full scalar
+/// multiplication, and the points are normalized [`NORMALIZE_BATCH`] at a time. The keys
+/// are exactly as unpredictable as `start`, so it must come from a cryptographically
+/// secure RNG.
+pub fn search_keys(
+    pattern: &[u8],
+    target: KeyTarget,
+    start: &SecretKey,
+    count: u32,
+) -> (Option<FoundKey>, u32) {
+    let mut scalar: Scalar = *start.to_nonzero_scalar();
+    let mut point = ProjectivePoint::GENERATOR * scalar;
+    let mut tried = 0;
+
+    while tried < count {
+        let len = (count - tried).min(NORMALIZE_BATCH as u32) as usize;
+
+        // the unused tail stays the identity, which normalizes without an inversion
+        let mut points = [ProjectivePoint::IDENTITY; NORMALIZE_BATCH];
+        for slot in &mut points[..len] {
+            *slot = point;
+            point += ProjectivePoint::GENERATOR;
+        }
+        let affine = ProjectivePoint::batch_normalize(&points);
+
+        for (i, affine) in affine[..len].iter().enumerate() {
+            let key = scalar + Scalar::from(i as u64);
+
+            // the identity has no address, skip it in the unlikely event we wrap onto it
+            if bool::from(key.is_zero()) {
+                continue;
+            }
+
+            let encoded = affine.to_encoded_point(false);
+            let address = Address::from_raw_public_key(&encoded.as_bytes()[1..]);
+
+            let target = target.address(&address);
+
+            if matches_pattern(pattern, &target) {
+                let found = FoundKey {
+                    secret: key.to_bytes().into(),
+                    address,
+                    target,
+                };
+                return (Some(found), tried + i as u32 + 1);
+            }
+        }
+
+        scalar += Scalar::from(len as u64);
+        tried += len as u32;
+    }
+
+    (None, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    fn secret(n: u8) -> SecretKey {
+        let mut bytes = [0u8; 32];
+        bytes[31] = n;
+        SecretKey::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn address_of_known_key() {
+        assert_eq!(
+            address_of(&secret(1)),
+            address!("7E5F4552091A69125d5DfCb7b8C2659029395Bdf")
+        );
+    }
+
+    #[test]
+    fn search_keys_steps_through_consecutive_keys() {
+        // private key 3 controls 0x6813Eb9362372EEF6200f3b1dbC3f819671cBA69
+        let (found, tried) = search_keys(&[0x68, 0x13], KeyTarget::Account, &secret(1), 10);
+        let found = found.unwrap();
+        assert_eq!(tried, 3);
+        assert_eq!(found.secret, secret(3).to_bytes().as_slice());
+        assert_eq!(found.address, address_of(&secret(3)));
+
+        let (found, tried) = search_keys(&[0x68, 0x13], KeyTarget::Account, &secret(4), 10);
+        assert!(found.is_none());
+        assert_eq!(tried, 10);
+    }
+
+    #[test]
+    fn search_keys_crosses_normalize_batches() {
+        // the first key of the second batch, private key 257
+        let mut bytes = [0u8; 32];
+        bytes[30..].copy_from_slice(&(NORMALIZE_BATCH as u16 + 1).to_be_bytes());
+        let expected = address_of(&SecretKey::from_slice(&bytes).unwrap());
+        let (found, tried) = search_keys(&expected[..4], KeyTarget::Account, &secret(1), 1000);
+        let found = found.unwrap();
+        assert_eq!(tried, NORMALIZE_BATCH as u32 + 1);
+        assert_eq!(found.address, expected);
+    }
+
+    #[test]
+    fn search_keys_matches_create_address() {
+        let deployer = address_of(&secret(2));
+        let contract = deployer.create(1);
+
+        let (found, tried) = search_keys(&contract[..2], KeyTarget::Create(1), &secret(1), 10);
+        let found = found.unwrap();
+        assert_eq!(tried, 2);
+        assert_eq!(found.address, deployer);
+        assert_eq!(found.target, contract);
+    }
+}
@@ -0,0 +1,665 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Number of nibbles in an address.
+const ADDRESS_NIBBLES: usize = 40;
+
+/// A regex-like expression over the 40 hex nibbles of an address, e.g.
+/// `^0x(dead|beef)[0-9]{4}` or `^(.)\1{3}` for four identical leading nibbles.
+///
+/// Supported are hex digits, `.`, classes like `[0-9a]` or `[^f]`, groups with `|`,
+/// the quantifiers `?`, `*`, `+`, `{n}`, `{n,}` and `{n,m}`, backreferences `\1` to
+/// `\9`, and the anchors `^` and `$` around the whole expression. Without `^`, the
+/// expression may match anywhere in the address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expression {
+    pub source: String,
+    pub root: Node,
+    pub start: bool,
+    pub end: bool,
+    pub groups: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Node {
+    /// One nibble out of a set, bit `n` allows the value `n`.
+    Nibble(u16),
+    Seq(Vec<Node>),
+    Alt(Vec<Node>),
+    /// A capturing group with its index, counted from 0.
+    Group(usize, Box<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// The nibbles matched by the group with the given index.
+    Backref(usize),
+}
+
+type Captures = Vec<Option<(usize, usize)>>;
+
+impl Expression {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let source = input.trim();
+        let mut rest = source;
+
+        let start = rest.starts_with('^');
+        if start {
+            rest = &rest[1..];
+        }
+        rest = rest
+            .strip_prefix("0x")
+            .or_else(|| rest.strip_prefix("0X"))
+            .unwrap_or(rest);
+        let end = rest.ends_with('$') && !rest.ends_with("\\$");
+        if end {
+            rest = &rest[..rest.len() - 1];
+        }
+
+        let mut parser = Parser {
+            chars: rest.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let root = parser.parse_alt()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!(
+                "Unexpected '{}' at position {} of pattern '{}'.",
+                c, parser.pos, source
+            ));
+        }
+        if parser.groups == 0 && root == Node::Seq(vec![]) {
+            return Err("Pattern cannot be empty.".to_string());
+        }
+        check_backrefs(&root, parser.groups)?;
+
+        Ok(Self {
+            source: source.to_string(),
+            root,
+            start,
+            end,
+            groups: parser.groups,
+        })
+    }
+
+    /// Nibble offset of the first match in `address`, if any.
+    pub fn find(&self, address: &Address) -> Option<usize> {
+        let mut nibbles = [0u8; ADDRESS_NIBBLES];
+        for (pair, byte) in nibbles.chunks_exact_mut(2).zip(address.iter()) {
+            pair[0] = byte >> 4;
+            pair[1] = byte & 0xf;
+        }
+        let last = if self.start { 0 } else { ADDRESS_NIBBLES };
+
+        // a failed attempt restores every capture it set, so one buffer serves all starts
+        let mut captures = vec![None; self.groups];
+        (0..=last).find(|&pos| {
+            match_node(&self.root, &nibbles, pos, &mut captures, &mut |end, _| {
+                !self.end || end == ADDRESS_NIBBLES
+            })
+        })
+    }
+
+    pub fn matches(&self, address: &Address) -> bool {
+        self.find(address).is_some()
+    }
+
+    /// Rough probability that a random address matches, assuming alternatives do not
+    /// overlap and counting variable repetitions at their minimum.
+    pub fn probability(&self) -> f64 {
+        let (probability, width) = estimate(&self.root);
+        if self.start || self.end {
+            return probability;
+        }
+        let positions = (ADDRESS_NIBBLES + 1).saturating_sub(width) as f64;
+        -(positions * (-probability.min(1.0)).ln_1p()).exp_m1()
+    }
+
+    /// OpenCL C defining `matchesExpression(d)` for the 20-byte address `d`.
+    ///
+    /// Only expressions where every part has a fixed length can be lowered, since the
+    /// kernel checks each nibble at a position known at compile time.
+    pub fn to_opencl(&self) -> Result<String, String> {
+        let mut lowering = Lowering {
+            groups: vec![None; self.groups],
+        };
+        let (predicate, width) = lowering
+            .lower(&self.root, 0)
+            .map_err(|err| format!("Pattern '{}' cannot run on the GPU: {}", self.source, err))?;
+
+        if width > ADDRESS_NIBBLES {
+            return Err(format!(
+                "Pattern '{}' is longer than an address.",
+                self.source
+            ));
+        }
+        let (first, last) = match (self.start, self.end) {
+            (true, true) if width != ADDRESS_NIBBLES => {
+                return Err(format!(
+                    "Pattern '{}' is anchored at both ends but does not span all 40 nibbles.",
+                    self.source
+                ))
+            }
+            (true, _) => (0, 0),
+            (false, true) => (ADDRESS_NIBBLES - width, ADDRESS_NIBBLES - width),
+            (false, false) => (0, ADDRESS_NIBBLES - width),
+        };
+
+        let mut src = String::new();
+        writeln!(src, "#define EXPRESSION").unwrap();
+        writeln!(
+            src,
+            "#define ADDRESS_NIBBLE(d, n) (((n) & 1) ? ((d)[(n) >> 1] & 0xf) : ((d)[(n) >> 1] >> 4))"
+        )
+        .unwrap();
+        writeln!(
+            src,
+            "static inline bool matchesExpressionAt(uchar const *d, uint const p) {{\n  return {};\n}}",
+            predicate
+        )
+        .unwrap();
+        writeln!(
+            src,
+            "static inline bool matchesExpression(uchar const *d) {{\n  for (uint p = {}; p <= {}; ++p) {{\n    if (matchesExpressionAt(d, p))\n      return true;\n  }}\n  return false;\n}}",
+            first, last
+        )
+        .unwrap();
+        Ok(src)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(format!("Expected '{}' in pattern.", expected)),
+        }
+    }
+
+    fn parse_alt(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_seq()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.parse_seq()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn parse_seq(&mut self) -> Result<Node, String> {
+        let mut items = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            items.push(self.parse_quantifier(atom)?);
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else {
+            Node::Seq(items)
+        })
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('.') => Ok(Node::Nibble(0xffff)),
+            Some('(') => {
+                let index = self.groups;
+                self.groups += 1;
+                let inner = self.parse_alt()?;
+                self.expect(')')?;
+                Ok(Node::Group(index, Box::new(inner)))
+            }
+            Some('[') => self.parse_class(),
+            Some('\\') => match self.next().and_then(|c| c.to_digit(10)) {
+                Some(n) if n > 0 => Ok(Node::Backref(n as usize - 1)),
+                _ => Err("Only backreferences \\1 to \\9 can be escaped in a pattern.".to_string()),
+            },
+            Some(c) => match c.to_digit(16) {
+                Some(nibble) => Ok(Node::Nibble(1 << nibble)),
+                None => Err(format!("Unexpected '{}' in pattern.", c)),
+            },
+            None => Err("Unexpected end of pattern.".to_string()),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut set: u16 = 0;
+        loop {
+            let from = match self.next() {
+                Some(']') => break,
+                Some(c) => self.class_nibble(c)?,
+                None => return Err("Unterminated character class in pattern.".to_string()),
+            };
+            let to = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let c = self.next().unwrap_or(']');
+                self.class_nibble(c)?
+            } else {
+                from
+            };
+            if to < from {
+                return Err(format!("Invalid range {:x}-{:x} in pattern.", from, to));
+            }
+            for nibble in from..=to {
+                set |= 1 << nibble;
+            }
+        }
+
+        Ok(Node::Nibble(if negated { !set } else { set }))
+    }
+
+    fn class_nibble(&self, c: char) -> Result<u32, String> {
+        c.to_digit(16)
+            .ok_or_else(|| format!("Unexpected '{}' in character class.", c))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('?') => (0, Some(1)),
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.parse_count()?;
+                let max = if self.peek() == Some(',') {
+                    self.pos += 1;
+                    if self.peek() == Some('}') {
+                        None
+                    } else {
+                        Some(self.parse_count()?)
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') {
+                    return Err("Expected '}' in pattern.".to_string());
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err("Invalid repetition bounds in pattern.".to_string());
+                }
+                (min, max)
+            }
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    fn parse_count(&mut self) -> Result<usize, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<usize>() {
+            Ok(count) if count <= ADDRESS_NIBBLES => Ok(count),
+            Ok(_) => Err("Repetition count exceeds the 40 nibbles of an address.".to_string()),
+            Err(_) => Err("Expected a repetition count in pattern.".to_string()),
+        }
+    }
+}
+
+fn check_backrefs(node: &Node, groups: usize) -> Result<(), String> {
+    match node {
+        Node::Backref(index) if *index >= groups => Err(format!(
+            "Backreference \\{} refers to a missing group.",
+            index + 1
+        )),
+        Node::Nibble(_) | Node::Backref(_) => Ok(()),
+        Node::Seq(nodes) | Node::Alt(nodes) => nodes
+            .iter()
+            .try_for_each(|node| check_backrefs(node, groups)),
+        Node::Group(_, node) | Node::Repeat { node, .. } => check_backrefs(node, groups),
+    }
+}
+
+/// Backtracking match of `node` at `pos`, calling `next` with the end position of every
+/// way `node` can match until it accepts.
+fn match_node(
+    node: &Node,
+    nibbles: &[u8],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    match node {
+        Node::Nibble(set) => {
+            pos < nibbles.len() && set & (1 << nibbles[pos]) != 0 && next(pos + 1, captures)
+        }
+        Node::Seq(nodes) => match_seq(nodes, nibbles, pos, captures, next),
+        Node::Alt(branches) => branches
+            .iter()
+            .any(|branch| match_node(branch, nibbles, pos, captures, next)),
+        Node::Group(index, inner) => {
+            let index = *index;
+            match_node(inner, nibbles, pos, captures, &mut |end, captures| {
+                let previous = captures[index];
+                captures[index] = Some((pos, end));
+                if next(end, captures) {
+                    return true;
+                }
+                captures[index] = previous;
+                false
+            })
+        }
+        Node::Repeat { node, min, max } => {
+            match_repeat(node, *min, *max, 0, nibbles, pos, captures, next)
+        }
+        Node::Backref(index) => match captures[*index] {
+            Some((from, to)) => {
+                let end = pos + (to - from);
+                end <= nibbles.len()
+                    && nibbles[from..to] == nibbles[pos..end]
+                    && next(end, captures)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_seq(
+    nodes: &[Node],
+    nibbles: &[u8],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        None => next(pos, captures),
+        Some((first, rest)) => match_node(first, nibbles, pos, captures, &mut |end, captures| {
+            match_seq(rest, nibbles, end, captures, next)
+        }),
+    }
+}
+
+/// Greedy repetition, having matched `count` times so far.
+#[allow(clippy::too_many_arguments)]
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    nibbles: &[u8],
+    pos: usize,
+    captures: &mut Captures,
+    next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max)
+        && match_node(node, nibbles, pos, captures, &mut |end, captures| {
+            // an empty iteration cannot make progress, so stop repeating there
+            end != pos && match_repeat(node, min, max, count + 1, nibbles, end, captures, next)
+        })
+    {
+        return true;
+    }
+    count >= min && next(pos, captures)
+}
+
+/// Probability and width (in nibbles) of `node`, see [`Expression::probability`].
+fn estimate(node: &Node) -> (f64, usize) {
+    match node {
+        Node::Nibble(set) => (set.count_ones() as f64 / 16.0, 1),
+        Node::Seq(nodes) => nodes
+            .iter()
+            .map(estimate)
+            .fold((1.0, 0), |acc, (p, w)| (acc.0 * p, acc.1 + w)),
+        Node::Alt(branches) => {
+            let estimates: Vec<_> = branches.iter().map(estimate).collect();
+            let probability = estimates.iter().map(|(p, _)| p).sum::<f64>().min(1.0);
+            let width = estimates.iter().map(|(_, w)| *w).min().unwrap_or(0);
+            (probability, width)
+        }
+        Node::Group(_, node) => estimate(node),
+        Node::Repeat { node, min, .. } => {
+            let (p, w) = estimate(node);
+            (p.powi(*min as i32), w * min)
+        }
+        // a single nibble per repeated nibble, as the width of the group is unknown here
+        Node::Backref(_) => (1.0 / 16.0, 1),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct LoweredGroup {
+    offset: usize,
+    width: usize,
+    /// Whether the group is inside an alternative that has been left, so it may not
+    /// have matched.
+    conditional: bool,
+}
+
+struct Lowering {
+    groups: Vec<Option<LoweredGroup>>,
+}
+
+impl Lowering {
+    /// C expression checking `node` at nibble `offset` from the match start `p`, and
+    /// the number of nibbles it spans.
+    fn lower(&mut self, node: &Node, offset: usize) -> Result<(String, usize), String> {
+        match node {
+            Node::Nibble(0xffff) => Ok(("true".to_string(), 1)),
+            Node::Nibble(set) if set.count_ones() == 1 => Ok((
+                format!(
+                    "(ADDRESS_NIBBLE(d, p + {}) == {}u)",
+                    offset,
+                    set.trailing_zeros()
+                ),
+                1,
+            )),
+            Node::Nibble(set) => Ok((
+                format!(
+                    "((0x{:04x}u >> ADDRESS_NIBBLE(d, p + {})) & 1u)",
+                    set, offset
+                ),
+                1,
+            )),
+            Node::Seq(nodes) => {
+                let mut terms = vec![];
+                let mut width = 0;
+                for node in nodes {
+                    let (term, node_width) = self.lower(node, offset + width)?;
+                    terms.push(term);
+                    width += node_width;
+                }
+                Ok((conjunction(terms), width))
+            }
+            Node::Alt(branches) => {
+                let mut terms = vec![];
+                let mut width = None;
+                for branch in branches {
+                    let before = self.groups.clone();
+                    let (term, branch_width) = self.lower(branch, offset)?;
+                    // the groups of this branch may be unset when another one matches
+                    for (group, before) in self.groups.iter_mut().zip(before) {
+                        if let Some(group) = group.as_mut().filter(|group| Some(**group) != before)
+                        {
+                            group.conditional = true;
+                        }
+                    }
+                    if width.is_some_and(|width| width != branch_width) {
+                        return Err("alternatives must all have the same length.".to_string());
+                    }
+                    terms.push(term);
+                    width = Some(branch_width);
+                }
+                Ok((format!("({})", terms.join(" || ")), width.unwrap_or(0)))
+            }
+            Node::Group(index, inner) => {
+                let (term, width) = self.lower(inner, offset)?;
+                self.groups[*index] = Some(LoweredGroup {
+                    offset,
+                    width,
+                    conditional: false,
+                });
+                Ok((term, width))
+            }
+            Node::Repeat { node, min, max } => {
+                if *max != Some(*min) {
+                    return Err("repetitions must have a fixed count like {4}.".to_string());
+                }
+                let mut terms = vec![];
+                let mut width = 0;
+                for _ in 0..*min {
+                    let (term, node_width) = self.lower(node, offset + width)?;
+                    terms.push(term);
+                    width += node_width;
+                }
+                Ok((conjunction(terms), width))
+            }
+            Node::Backref(index) => match self.groups[*index] {
+                Some(group) if !group.conditional => {
+                    let terms = (0..group.width)
+                        .map(|i| {
+                            format!(
+                                "(ADDRESS_NIBBLE(d, p + {}) == ADDRESS_NIBBLE(d, p + {}))",
+                                offset + i,
+                                group.offset + i
+                            )
+                        })
+                        .collect();
+                    Ok((conjunction(terms), group.width))
+                }
+                Some(_) => Err(format!(
+                    "backreference \\{} refers to a group in another alternative.",
+                    index + 1
+                )),
+                None => Err(format!(
+                    "backreference \\{} comes before its group.",
+                    index + 1
+                )),
+            },
+        }
+    }
+}
+
+fn conjunction(terms: Vec<String>) -> String {
+    if terms.is_empty() {
+        "true".to_string()
+    } else {
+        format!("({})", terms.join(" && "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> Address {
+        hex.parse().unwrap()
+    }
+
+    #[test]
+    fn matches_anchored_alternatives() {
+        let expression = Expression::parse("^0x(dead|beef)[0-9]{4}").unwrap();
+        assert!(expression.matches(&address("0xdead1234ffffffffffffffffffffffffffffffff")));
+        assert!(expression.matches(&address("0xBEEF0000ffffffffffffffffffffffffffffffff")));
+        assert!(!expression.matches(&address("0xdead12a4ffffffffffffffffffffffffffffffff")));
+        assert!(!expression.matches(&address("0x0dead1234fffffffffffffffffffffffffffffff")));
+    }
+
+    #[test]
+    fn matches_backreferences_and_positions() {
+        let identical = Expression::parse(r"^(.)\1{3}").unwrap();
+        assert!(identical.matches(&address("0x7777a000000000000000000000000000000000ff")));
+        assert!(!identical.matches(&address("0x777a0000000000000000000000000000000000ff")));
+
+        let anywhere = Expression::parse("c0f+ee$").unwrap();
+        assert_eq!(
+            anywhere.find(&address("0x1111111111111111111111111111111c0fffffee")),
+            Some(31)
+        );
+        assert_eq!(
+            anywhere.find(&address("0x1111111111111111111111111111111c0fffffe1")),
+            None
+        );
+
+        // the captures of the failed starts must not leak into the later ones
+        let pair = Expression::parse(r"(.)\1a").unwrap();
+        assert_eq!(
+            pair.find(&address("0x1212334bb5bba12121212121212121212121212f")),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        for pattern in [
+            "",
+            "^",
+            "(ab",
+            "ab)",
+            "[a-",
+            "g",
+            r"\1(a)x\2",
+            "a{41}",
+            "a{3,2}",
+        ] {
+            assert!(Expression::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn lowers_fixed_length_patterns() {
+        let src = Expression::parse(r"^(.)\1{3}")
+            .unwrap()
+            .to_opencl()
+            .unwrap();
+        assert!(src.contains("for (uint p = 0; p <= 0; ++p)"));
+        assert!(src.contains("ADDRESS_NIBBLE(d, p + 3) == ADDRESS_NIBBLE(d, p + 0)"));
+
+        let src = Expression::parse("c0ffee").unwrap().to_opencl().unwrap();
+        assert!(src.contains("for (uint p = 0; p <= 34; ++p)"));
+
+        // a backreference within the alternative of its group is fine
+        assert!(Expression::parse(r"^(.)\1|c0").unwrap().to_opencl().is_ok());
+    }
+
+    #[test]
+    fn reports_patterns_that_cannot_run_on_the_gpu() {
+        for pattern in ["^c0f+ee", "^(a|bc)", r"^(a|(b))\2", "^0{4}$"] {
+            let expression = Expression::parse(pattern).unwrap();
+            let err = expression.to_opencl().unwrap_err();
+            assert!(err.contains(pattern), "{}", err);
+        }
+    }
+
+    #[test]
+    fn estimates_probability() {
+        let expression = Expression::parse("^[0-7]0").unwrap();
+        assert_eq!(expression.probability(), 0.5 / 16.0);
+    }
+}
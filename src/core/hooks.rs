@@ -0,0 +1,85 @@
+use super::pattern::Pattern;
+
+/// Uniswap v4 hook permissions and the address bit the PoolManager reads for each,
+/// see `Hooks.sol` in v4-core.
+pub const HOOK_FLAGS: [(&str, u16); 14] = [
+    ("beforeInitialize", 1 << 13),
+    ("afterInitialize", 1 << 12),
+    ("beforeAddLiquidity", 1 << 11),
+    ("afterAddLiquidity", 1 << 10),
+    ("beforeRemoveLiquidity", 1 << 9),
+    ("afterRemoveLiquidity", 1 << 8),
+    ("beforeSwap", 1 << 7),
+    ("afterSwap", 1 << 6),
+    ("beforeDonate", 1 << 5),
+    ("afterDonate", 1 << 4),
+    ("beforeSwapReturnDelta", 1 << 3),
+    ("afterSwapReturnDelta", 1 << 2),
+    ("afterAddLiquidityReturnDelta", 1 << 1),
+    ("afterRemoveLiquidityReturnDelta", 1 << 0),
+];
+
+/// The low 14 address bits that hold the hook permissions.
+pub const HOOK_FLAGS_MASK: u16 = (1 << 14) - 1;
+
+/// Parse a comma separated list of hook callbacks (e.g., 'beforeSwap,afterSwap') into
+/// their flag bits. `none` selects a hook without any callbacks.
+pub fn parse_hook_flags(input: &str) -> Result<u16, String> {
+    if input.trim() == "none" {
+        return Ok(0);
+    }
+
+    let mut flags = 0;
+    for name in input.split(',').map(str::trim) {
+        let (_, flag) = HOOK_FLAGS
+            .iter()
+            .find(|(flag_name, _)| flag_name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown hook callback: '{}'.", name))?;
+        flags |= flag;
+    }
+    Ok(flags)
+}
+
+/// Pattern for addresses whose low 14 bits are exactly `flags`, so the hook gets the
+/// selected callbacks and no others.
+pub fn hook_pattern(flags: u16) -> Pattern {
+    let mut pattern = Pattern::any();
+    pattern.mask[18..].copy_from_slice(&HOOK_FLAGS_MASK.to_be_bytes());
+    pattern.value[18..].copy_from_slice(&(flags & HOOK_FLAGS_MASK).to_be_bytes());
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn parses_flags() {
+        assert_eq!(parse_hook_flags("none").unwrap(), 0);
+        assert_eq!(
+            parse_hook_flags("beforeSwap, afterSwap").unwrap(),
+            (1 << 7) | (1 << 6)
+        );
+        assert_eq!(
+            parse_hook_flags("beforeInitialize,afterRemoveLiquidityReturnDelta").unwrap(),
+            0x2001
+        );
+        assert!(parse_hook_flags("beforeSwap,onSwap").is_err());
+    }
+
+    #[test]
+    fn pattern_requires_exact_flags() {
+        let pattern = hook_pattern(parse_hook_flags("beforeSwap,afterSwap").unwrap());
+        assert_eq!(pattern.constrained_bits(), 14);
+
+        let mut address = Address::repeat_byte(0xab);
+        address[18] = 0xc0;
+        address[19] = 0xc0;
+        assert!(pattern.matches(&address));
+
+        // an extra permission bit makes the PoolManager call a callback that is not there
+        address[19] = 0xc1;
+        assert!(!pattern.matches(&address));
+    }
+}
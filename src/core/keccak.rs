@@ -0,0 +1,395 @@
+use std::sync::OnceLock;
+
+/// Number of 64-bit lanes absorbed per Keccak-256 block (136 bytes).
+pub const RATE_LANES: usize = 17;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets of the rho step, in the order lanes are visited by pi.
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// Lane visited after each step of pi.
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The Keccak-f[1600] permutation over 25 little-endian lanes.
+pub fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // theta
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut last = state[1];
+        for (&lane, &rotation) in PI.iter().zip(RHO.iter()) {
+            let next = state[lane];
+            state[lane] = last.rotate_left(rotation);
+            last = next;
+        }
+
+        // chi
+        for y in 0..5 {
+            let row = [
+                state[5 * y],
+                state[5 * y + 1],
+                state[5 * y + 2],
+                state[5 * y + 3],
+                state[5 * y + 4],
+            ];
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        state[0] ^= round_constant;
+    }
+}
+
+/// Keccak-f[1600] with every lane a SIMD vector of `$lanes` states, given the
+/// vector operations `$xor(a, b)`, `$chi(a, b, c)` = `a ^ (!b & c)`, `$rol(a, n)` and
+/// `$splat(x)`.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+macro_rules! keccak_f_vector {
+    ($a:ident, $xor:ident, $chi:ident, $rol:ident, $splat:ident) => {
+        for round_constant in ROUND_CONSTANTS {
+            // theta
+            let mut c = [$a[0]; 5];
+            for (x, c) in c.iter_mut().enumerate() {
+                *c = $xor(
+                    $xor($xor($a[x], $a[x + 5]), $xor($a[x + 10], $a[x + 15])),
+                    $a[x + 20],
+                );
+            }
+            for x in 0..5 {
+                let d = $xor(c[(x + 4) % 5], $rol(c[(x + 1) % 5], 1));
+                for y in 0..5 {
+                    $a[x + 5 * y] = $xor($a[x + 5 * y], d);
+                }
+            }
+
+            // rho and pi
+            let mut last = $a[1];
+            for (&lane, &rotation) in PI.iter().zip(RHO.iter()) {
+                let next = $a[lane];
+                $a[lane] = $rol(last, rotation);
+                last = next;
+            }
+
+            // chi
+            for y in 0..5 {
+                let row = [
+                    $a[5 * y],
+                    $a[5 * y + 1],
+                    $a[5 * y + 2],
+                    $a[5 * y + 3],
+                    $a[5 * y + 4],
+                ];
+                for x in 0..5 {
+                    $a[5 * y + x] = $chi(row[x], row[(x + 1) % 5], row[(x + 2) % 5]);
+                }
+            }
+
+            // iota
+            $a[0] = $xor($a[0], $splat(round_constant));
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{PI, RHO, ROUND_CONSTANTS};
+    use std::arch::x86_64::*;
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn xor256(a: __m256i, b: __m256i) -> __m256i {
+        _mm256_xor_si256(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn chi256(a: __m256i, b: __m256i, c: __m256i) -> __m256i {
+        _mm256_xor_si256(a, _mm256_andnot_si256(b, c))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn rol256(a: __m256i, n: u32) -> __m256i {
+        // AVX2 has no 64-bit rotate, so shift both ways
+        let left = _mm256_sllv_epi64(a, _mm256_set1_epi64x(n as i64));
+        let right = _mm256_srlv_epi64(a, _mm256_set1_epi64x(64 - n as i64));
+        _mm256_or_si256(left, right)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn splat256(x: u64) -> __m256i {
+        _mm256_set1_epi64x(x as i64)
+    }
+
+    /// Keccak-f[1600] over 4 states at a time, `N` has to be a multiple of 4.
+    #[target_feature(enable = "avx2")]
+    pub fn keccak_f_avx2<const N: usize>(state: &mut [[u64; N]; 25]) {
+        for chunk in (0..N).step_by(4) {
+            let mut a = [_mm256_setzero_si256(); 25];
+            for (a, lane) in a.iter_mut().zip(state.iter()) {
+                // SAFETY: `chunk + 4 <= N`, so the 4 words are within the lane
+                *a = unsafe { _mm256_loadu_si256(lane[chunk..].as_ptr().cast()) };
+            }
+            keccak_f_vector!(a, xor256, chi256, rol256, splat256);
+            for (a, lane) in a.iter().zip(state.iter_mut()) {
+                unsafe { _mm256_storeu_si256(lane[chunk..].as_mut_ptr().cast(), *a) };
+            }
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn xor512(a: __m512i, b: __m512i) -> __m512i {
+        _mm512_xor_si512(a, b)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn chi512(a: __m512i, b: __m512i, c: __m512i) -> __m512i {
+        // a ^ (!b & c) as a single ternary logic instruction
+        _mm512_ternarylogic_epi64::<0xd2>(a, b, c)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn rol512(a: __m512i, n: u32) -> __m512i {
+        _mm512_rolv_epi64(a, _mm512_set1_epi64(n as i64))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    fn splat512(x: u64) -> __m512i {
+        _mm512_set1_epi64(x as i64)
+    }
+
+    /// Keccak-f[1600] over 8 states at a time, `N` has to be a multiple of 8.
+    #[target_feature(enable = "avx512f")]
+    pub fn keccak_f_avx512<const N: usize>(state: &mut [[u64; N]; 25]) {
+        for chunk in (0..N).step_by(8) {
+            let mut a = [_mm512_setzero_si512(); 25];
+            for (a, lane) in a.iter_mut().zip(state.iter()) {
+                // SAFETY: `chunk + 8 <= N`, so the 8 words are within the lane
+                *a = unsafe { _mm512_loadu_si512(lane[chunk..].as_ptr().cast()) };
+            }
+            keccak_f_vector!(a, xor512, chi512, rol512, splat512);
+            for (a, lane) in a.iter().zip(state.iter_mut()) {
+                unsafe { _mm512_storeu_si512(lane[chunk..].as_mut_ptr().cast(), *a) };
+            }
+        }
+    }
+}
+
+/// WebAssembly has no runtime feature detection, so this is only compiled into builds
+/// with `-C target-feature=+simd128`.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use super::{PI, RHO, ROUND_CONSTANTS};
+    use std::arch::wasm32::*;
+
+    #[inline]
+    fn chi(a: v128, b: v128, c: v128) -> v128 {
+        v128_xor(a, v128_andnot(c, b))
+    }
+
+    #[inline]
+    fn rol(a: v128, n: u32) -> v128 {
+        v128_or(i64x2_shl(a, n), u64x2_shr(a, 64 - n))
+    }
+
+    /// Keccak-f[1600] over 2 states at a time, `N` has to be a multiple of 2.
+    pub fn keccak_f_simd128<const N: usize>(state: &mut [[u64; N]; 25]) {
+        for chunk in (0..N).step_by(2) {
+            let mut a = [u64x2_splat(0); 25];
+            for (a, lane) in a.iter_mut().zip(state.iter()) {
+                // SAFETY: `chunk + 2 <= N`, so the 2 words are within the lane
+                *a = unsafe { v128_load(lane[chunk..].as_ptr().cast()) };
+            }
+            keccak_f_vector!(a, v128_xor, chi, rol, u64x2_splat);
+            for (a, lane) in a.iter().zip(state.iter_mut()) {
+                unsafe { v128_store(lane[chunk..].as_mut_ptr().cast(), *a) };
+            }
+        }
+    }
+}
+
+/// One state after the other with the scalar permutation.
+fn keccak_f_portable<const N: usize>(state: &mut [[u64; N]; 25]) {
+    for j in 0..N {
+        let mut single: [u64; 25] = std::array::from_fn(|i| state[i][j]);
+        keccak_f(&mut single);
+        for (lane, value) in state.iter_mut().zip(single) {
+            lane[j] = value;
+        }
+    }
+}
+
+/// Instruction set used by [`keccak_f_batch`], detected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Simd {
+    Avx512,
+    Avx2,
+    /// WebAssembly SIMD, two states per 128-bit register.
+    Simd128,
+    Portable,
+}
+
+impl Simd {
+    /// The widest instruction set of the CPU, detected once and cached for the process.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<Simd> = OnceLock::new();
+        *DETECTED.get_or_init(Self::detect_uncached)
+    }
+
+    fn detect_uncached() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::arch::is_x86_feature_detected!("avx512f") {
+                return Simd::Avx512;
+            }
+            if std::arch::is_x86_feature_detected!("avx2") {
+                return Simd::Avx2;
+            }
+        }
+        if cfg!(all(target_arch = "wasm32", target_feature = "simd128")) {
+            return Simd::Simd128;
+        }
+        Simd::Portable
+    }
+}
+
+/// Keccak-f[1600] over `N` states stored lane by lane, i.e. `state[i][j]` is lane `i`
+/// of state `j`, using the widest instruction set supported by the CPU.
+pub fn keccak_f_batch<const N: usize>(state: &mut [[u64; N]; 25]) {
+    keccak_f_batch_with(Simd::detect(), state)
+}
+
+fn keccak_f_batch_with<const N: usize>(simd: Simd, state: &mut [[u64; N]; 25]) {
+    match simd {
+        // SAFETY: callers only pass instruction sets up to the one detected on the CPU
+        #[cfg(target_arch = "x86_64")]
+        Simd::Avx512 if N.is_multiple_of(8) => unsafe { x86::keccak_f_avx512(state) },
+        #[cfg(target_arch = "x86_64")]
+        Simd::Avx512 | Simd::Avx2 if N.is_multiple_of(4) => unsafe { x86::keccak_f_avx2(state) },
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        Simd::Simd128 if N.is_multiple_of(2) => wasm::keccak_f_simd128(state),
+        _ => keccak_f_portable(state),
+    }
+}
+
+/// State after absorbing a single padded block of at most 135 message bytes, before
+/// the permutation is applied.
+pub fn padded_block(message: &[u8]) -> [u64; 25] {
+    let mut block = [0u8; RATE_LANES * 8];
+    block[..message.len()].copy_from_slice(message);
+    block[message.len()] ^= 0x01;
+    block[RATE_LANES * 8 - 1] ^= 0x80;
+    block_lanes(&block)
+}
+
+/// The lanes of up to 136 bytes of a block, without any padding.
+pub fn block_lanes(block: &[u8]) -> [u64; 25] {
+    let mut lanes = [0u64; 25];
+    for (lane, chunk) in lanes.iter_mut().zip(block.chunks(8)) {
+        let mut bytes = [0u8; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        *lane = u64::from_le_bytes(bytes);
+    }
+    lanes
+}
+
+/// The first 32 bytes of the state, i.e. the Keccak-256 digest.
+pub fn digest(state: &[u64; 25]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (chunk, lane) in out.chunks_mut(8).zip(state) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    #[test]
+    fn matches_keccak256() {
+        for len in [0, 1, 85, 135] {
+            let message: Vec<u8> = (0..len).map(|i| i as u8 ^ 0x5a).collect();
+            let mut state = padded_block(&message);
+            keccak_f(&mut state);
+            assert_eq!(digest(&state), keccak256(&message).0);
+        }
+    }
+
+    #[test]
+    fn batch_matches_scalar() {
+        let states: Vec<[u64; 25]> = (0..8u64)
+            .map(|j| std::array::from_fn(|i| (i as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15) ^ j))
+            .collect();
+        let mut expected = states.clone();
+        expected.iter_mut().for_each(keccak_f);
+
+        // every instruction set up to the detected one is supported
+        let supported = match Simd::detect() {
+            Simd::Avx512 => &[Simd::Portable, Simd::Avx2, Simd::Avx512][..],
+            Simd::Avx2 => &[Simd::Portable, Simd::Avx2][..],
+            Simd::Simd128 => &[Simd::Portable, Simd::Simd128][..],
+            Simd::Portable => &[Simd::Portable][..],
+        };
+        for &simd in supported {
+            let mut batch: [[u64; 8]; 25] =
+                std::array::from_fn(|i| std::array::from_fn(|j| states[j][i]));
+            keccak_f_batch_with(simd, &mut batch);
+            for (j, expected) in expected.iter().enumerate() {
+                let got: [u64; 25] = std::array::from_fn(|i| batch[i][j]);
+                assert_eq!(&got, expected, "{:?}", simd);
+            }
+        }
+    }
+}
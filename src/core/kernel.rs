@@ -0,0 +1,221 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt::Write, fs};
+
+use super::{
+    lanes::{LaneLayout, MessageLanes, CREATE2_SALT_OFFSET},
+    pattern::Pattern,
+    score::Ranking,
+    MinerConfig,
+};
+
+/// Address search shared by all kernels, following the Keccak-f[1600] permutation.
+static HASH_MESSAGE_SRC: &str = include_str!("../kernels/hash_message.cl");
+
+/// The bundled Keccak-f[1600] permutations by name, the first one being the default.
+pub static KERNELS: &[(&str, &str)] = &[
+    ("unrolled", include_str!("../kernels/keccak256.cl")),
+    ("looped", include_str!("../kernels/keccak256_2.cl")),
+];
+
+/// A Keccak-f[1600] permutation for the kernel, defining `keccakf` and `keccakfFull`
+/// as described in `hash_message.cl`.
+#[derive(Debug, Clone)]
+pub struct KeccakKernel {
+    pub name: String,
+    pub src: Cow<'static, str>,
+}
+
+impl Default for KeccakKernel {
+    fn default() -> Self {
+        let (name, src) = KERNELS[0];
+        Self {
+            name: name.to_string(),
+            src: Cow::Borrowed(src),
+        }
+    }
+}
+
+impl KeccakKernel {
+    /// The bundled kernel `name`, or the kernel in the `.cl` file at path `name`.
+    pub fn load(name: &str) -> Result<Self, String> {
+        if let Some(&(name, src)) = KERNELS.iter().find(|(bundled, _)| *bundled == name) {
+            return Ok(Self {
+                name: name.to_string(),
+                src: Cow::Borrowed(src),
+            });
+        }
+
+        if !name.ends_with(".cl") {
+            let bundled: Vec<_> = KERNELS.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "Unknown kernel '{}', expected one of {} or the path of a .cl file.",
+                name,
+                bundled.join(", ")
+            ));
+        }
+
+        let src = fs::read_to_string(name)
+            .map_err(|err| format!("Could not read the kernel '{}': {}", name, err))?;
+        Ok(Self {
+            name: name.to_string(),
+            src: Cow::Owned(src),
+        })
+    }
+}
+
+/// Compare the address a 64-bit lane of the Keccak state at a time against the pattern
+/// compiled into the source. The address is bytes 12..32 of the little-endian state,
+/// i.e. the upper half of lane 1 and lanes 2 and 3, and lanes without any constrained
+/// bits are skipped entirely.
+fn pattern_words(pattern: &Pattern) -> String {
+    let mut value = [0u8; 32];
+    let mut mask = [0u8; 32];
+    value[12..].copy_from_slice(&pattern.value);
+    mask[12..].copy_from_slice(&pattern.mask);
+
+    let lanes: Vec<String> = (1..4)
+        .filter_map(|lane| {
+            let bytes = lane * 8..lane * 8 + 8;
+            let mask = u64::from_le_bytes(mask[bytes.clone()].try_into().unwrap());
+            let value = u64::from_le_bytes(value[bytes].try_into().unwrap());
+            (mask != 0).then(|| format!("(((s)[{}] & 0x{:x}UL) == 0x{:x}UL)", lane, mask, value))
+        })
+        .collect();
+
+    if lanes.is_empty() {
+        "true".to_string()
+    } else {
+        lanes.join(" && ")
+    }
+}
+
+/// Statements ORing source bytes into the zeroed bytes of the lanes of `s`, for
+/// `placements` of (byte offset in the state, byte offset in the source words). Bytes
+/// moving by the same shift between the same pair of words are placed at once.
+fn place_bytes(placements: &[(usize, usize)], word: impl Fn(usize) -> String) -> String {
+    let mut masks: BTreeMap<(usize, usize, isize), u64> = BTreeMap::new();
+    for &(dest, src) in placements {
+        let shift = 8 * ((dest % 8) as isize - (src % 8) as isize);
+        *masks.entry((dest / 8, src / 8, shift)).or_default() |= 0xff << (8 * (dest % 8));
+    }
+
+    masks
+        .into_iter()
+        .map(|((lane, src, shift), mask)| {
+            let shifted = match shift {
+                0 => word(src),
+                1.. => format!("({} << {})", word(src), shift),
+                _ => format!("({} >> {})", word(src), -shift),
+            };
+            format!("(s)[{}] |= {} & 0x{:x}UL;", lane, shifted, mask)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// OpenCL source of the `hashMessage` kernel searching for `config` with the Keccak-f
+/// permutation of `kernel`. With `specialized`, the pattern is compiled in, otherwise it
+/// is read from the pattern and mask buffers.
+pub fn kernel_src(
+    config: &MinerConfig,
+    specialized: bool,
+    kernel: &KeccakKernel,
+) -> Result<String, String> {
+    let mut src = String::with_capacity(2048 + kernel.src.len() + HASH_MESSAGE_SRC.len());
+
+    // everything else of the message is passed in as precomputed lanes
+    let lanes = MessageLanes::new(config, &vec![0; config.salt.random_len()]);
+    writeln!(
+        src,
+        "#define NONCE_LANES(s, n) do {{ {} }} while (0)",
+        place_bytes(&lanes.nonce_bytes, |_| "(n)".to_string())
+    )
+    .unwrap();
+
+    if specialized {
+        writeln!(
+            src,
+            "#define PATTERN_WORDS(s) ({})",
+            pattern_words(&config.pattern)
+        )
+        .unwrap();
+    }
+
+    if let Some(nibbles) = &config.contains {
+        let nibbles: Vec<String> = nibbles.iter().map(|x| format!("{}u", x)).collect();
+        writeln!(src, "#define CONTAINS_LEN {}", nibbles.len()).unwrap();
+        writeln!(src, "#define CONTAINS_NIBBLES {}", nibbles.join(", ")).unwrap();
+    }
+
+    match config.ranking {
+        Ranking::None => {}
+        Ranking::ZeroBytes => writeln!(src, "#define RANKING_ZERO_BYTES").unwrap(),
+        Ranking::RepeatedNibbles => writeln!(src, "#define RANKING_REPEATED_NIBBLES").unwrap(),
+        Ranking::RepeatedBytes => writeln!(src, "#define RANKING_REPEATED_BYTES").unwrap(),
+    }
+
+    match lanes.layout {
+        LaneLayout::Create2 => {}
+        LaneLayout::Safe => {
+            let salt_hash: Vec<_> = (0..32).map(|i| (CREATE2_SALT_OFFSET + i, i)).collect();
+            writeln!(src, "#define SAFE_PROXY").unwrap();
+            writeln!(
+                src,
+                "#define SALT_HASH_LANES(s, h) do {{ {} }} while (0)",
+                place_bytes(&salt_hash, |word| format!("(h)[{}]", word))
+            )
+            .unwrap();
+        }
+        LaneLayout::ZkSync => writeln!(src, "#define ZKSYNC").unwrap(),
+    }
+
+    if let Some(expression) = &config.expression {
+        src.push_str(&expression.to_opencl()?);
+    }
+
+    src.push_str(&kernel.src);
+    src.push_str(HASH_MESSAGE_SRC);
+
+    Ok(src)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{parse_config, RawConfig};
+
+    #[test]
+    fn loads_bundled_kernels_by_name() {
+        assert_eq!(KeccakKernel::default().name, "unrolled");
+        for (name, src) in KERNELS {
+            assert_eq!(KeccakKernel::load(name).unwrap().src, *src);
+        }
+        assert!(KeccakKernel::load("rolled").is_err());
+        assert!(KeccakKernel::load("missing.cl").is_err());
+    }
+
+    #[test]
+    fn kernel_src_defines_the_target() {
+        let config = parse_config(RawConfig {
+            factory: "0x0000000000000000000000000000000000000000".to_string(),
+            codehash: Some(format!("0x{}", "11".repeat(32))),
+            worksize: 1,
+            pattern: "abcd".to_string(),
+            salt: Some("zero:24,nonce".to_string()),
+            chain: Some("zksync".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let kernel = KeccakKernel::default();
+
+        let generic = kernel_src(&config, false, &kernel).unwrap();
+        assert!(generic.contains("#define ZKSYNC"));
+        assert!(!generic.contains("#define PATTERN_WORDS"));
+        assert!(generic.ends_with(HASH_MESSAGE_SRC));
+
+        // the first two address bytes are the upper half of the low word of lane 1
+        let specialized = kernel_src(&config, true, &kernel).unwrap();
+        assert!(specialized.contains(
+            "#define PATTERN_WORDS(s) ((((s)[1] & 0xffff00000000UL) == 0xcdab00000000UL))"
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::MinerConfig;
+
+/// Position of a salt in the keyspace of a run: the `offset`-th salt of batch `batch`
+/// of worker `worker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyspaceIndex {
+    pub worker: u32,
+    pub batch: u64,
+    pub offset: u32,
+}
+
+/// A salt as the salt prefix (the random segment of the salt template) it uses and the
+/// nonce within that prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaltPosition {
+    pub prefix: u64,
+    pub nonce: u64,
+}
+
+/// The salts searched by a run, enumerated deterministically from a single seed.
+///
+/// The batches of all workers are interleaved over the 2^64 nonces of a salt prefix,
+/// batch `b` of worker `w` being the `b * workers + w`-th batch of nonces. Once the
+/// nonces of a prefix are used up, the search continues with the next prefix. Workers
+/// therefore never search the same salt twice, and any salt found can be reproduced
+/// from the seed and its index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyspace {
+    pub seed: u64,
+    pub workers: u32,
+    /// Number of salts per batch.
+    pub batch_size: u32,
+    /// Random segment of the first salt prefix.
+    random: Vec<u8>,
+}
+
+impl Keyspace {
+    pub fn new(config: &MinerConfig, seed: u64, workers: u32, batch_size: u32) -> Self {
+        assert!(workers > 0, "worker count must not be zero");
+        assert!(batch_size > 0, "batch size must not be zero");
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut random = vec![0u8; config.salt.random_len()];
+        rng.fill(&mut random[..]);
+
+        Self {
+            seed,
+            workers,
+            batch_size,
+            random,
+        }
+    }
+
+    /// Number of whole batches fitting into the nonces of one salt prefix, the few
+    /// nonces left over are skipped so that a batch never spans two prefixes.
+    pub fn batches_per_prefix(&self) -> u64 {
+        ((1u128 << 64) / self.batch_size as u128) as u64
+    }
+
+    /// Number of distinct salt prefixes, `None` if there are more than a run can use.
+    pub fn prefix_count(&self) -> Option<u64> {
+        match self.random.len() {
+            0..=7 => Some(1 << (8 * self.random.len())),
+            _ => None,
+        }
+    }
+
+    /// Salt prefix and nonce of the salt at `index`, `None` once the keyspace of the
+    /// salt template is exhausted.
+    pub fn position(&self, index: KeyspaceIndex) -> Option<SaltPosition> {
+        assert!(index.worker < self.workers, "worker index out of range");
+        assert!(index.offset < self.batch_size, "batch offset out of range");
+
+        let batch = index.batch as u128 * self.workers as u128 + index.worker as u128;
+        let per_prefix = self.batches_per_prefix() as u128;
+        let prefix = u64::try_from(batch / per_prefix).ok()?;
+        if self.prefix_count().is_some_and(|count| prefix >= count) {
+            return None;
+        }
+
+        let nonce = (batch % per_prefix) as u64 * self.batch_size as u64 + index.offset as u64;
+        Some(SaltPosition { prefix, nonce })
+    }
+
+    /// Random segment of the salt template for salt prefix `prefix`, the seeded random
+    /// bytes plus `prefix` as a little-endian number, so prefixes never repeat.
+    pub fn random(&self, prefix: u64) -> Vec<u8> {
+        let mut random = self.random.clone();
+        let mut carry = prefix as u128;
+        for byte in random.iter_mut() {
+            carry += *byte as u128;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        random
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{parse_config, RawConfig};
+
+    fn config(salt: &str) -> MinerConfig {
+        parse_config(RawConfig {
+            factory: "0x0000000000000000000000000000000000000000".to_string(),
+            codehash: Some(format!("0x{}", "11".repeat(32))),
+            worksize: 1,
+            pattern: "00".to_string(),
+            salt: Some(salt.to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn workers_interleave_batches() {
+        let keyspace = Keyspace::new(&config("zero-caller"), 1, 4, 1000);
+        let position = |worker, batch, offset| {
+            keyspace
+                .position(KeyspaceIndex {
+                    worker,
+                    batch,
+                    offset,
+                })
+                .unwrap()
+        };
+
+        assert_eq!(position(0, 0, 999).nonce, 999);
+        assert_eq!(position(1, 0, 0).nonce, 1000);
+        assert_eq!(position(0, 1, 0).nonce, 4000);
+        assert_eq!(position(3, 1, 0).nonce, 7000);
+    }
+
+    #[test]
+    fn rotates_to_the_next_prefix() {
+        let keyspace = Keyspace::new(&config("zero-caller"), 1, 3, 1000);
+        let batches = keyspace.batches_per_prefix() as u128;
+
+        // the last whole batch of the first prefix and the first one of the second
+        let last = (batches - 1) as u64;
+        let position = |batch: u64| {
+            let worker = (batch % 3) as u32;
+            keyspace.position(KeyspaceIndex {
+                worker,
+                batch: batch / 3,
+                offset: 0,
+            })
+        };
+        assert_eq!(
+            position(last),
+            Some(SaltPosition {
+                prefix: 0,
+                nonce: last * 1000
+            })
+        );
+        assert_eq!(
+            position(last + 1),
+            Some(SaltPosition {
+                prefix: 1,
+                nonce: 0
+            })
+        );
+
+        assert_eq!(keyspace.random(0), keyspace.random(0));
+        assert_ne!(keyspace.random(0), keyspace.random(1));
+    }
+
+    #[test]
+    fn stops_after_the_last_prefix() {
+        let keyspace = Keyspace::new(&config("zero:24,nonce"), 1, 1, 1000);
+        assert_eq!(keyspace.prefix_count(), Some(1));
+        let index = KeyspaceIndex {
+            worker: 0,
+            batch: keyspace.batches_per_prefix(),
+            offset: 0,
+        };
+        assert_eq!(keyspace.position(index), None);
+    }
+}
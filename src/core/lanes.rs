@@ -0,0 +1,289 @@
+use alloy_primitives::Address;
+
+use super::{
+    keccak::{block_lanes, digest, keccak_f, keccak_f_batch, padded_block, RATE_LANES},
+    salt::SaltByte,
+    zksync, Derivation, MinerConfig,
+};
+
+/// Offset of the salt in the 85-byte CREATE2 message `0xff ++ factory ++ salt ++ codehash`.
+pub const CREATE2_SALT_OFFSET: usize = 21;
+
+/// Lane and bit shift of the salt within the CREATE2 message, which is not lane aligned.
+const SALT_LANE: usize = CREATE2_SALT_OFFSET / 8;
+const SALT_SHIFT: u32 = 8 * (CREATE2_SALT_OFFSET % 8) as u32;
+
+/// How the absorbed blocks turn into the address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneLayout {
+    /// A single block holding the CREATE2 message.
+    Create2,
+    /// The first block hashes the Safe salt nonce into the CREATE2 salt, which is then
+    /// placed into the CREATE2 message in the second block.
+    Safe,
+    /// The 160-byte zkSync preimage spans both blocks.
+    ZkSync,
+}
+
+/// The Keccak input of a run as 64-bit lanes, with everything but the nonce filled in.
+///
+/// Apart from the nonce, the whole message (0xff, factory, salt prefix and codehash)
+/// stays the same for a salt prefix, so it is assembled and padded once on the host
+/// instead of byte by byte for every hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageLanes {
+    pub layout: LaneLayout,
+    /// First padded block, with the nonce bytes zeroed.
+    pub first: [u64; 25],
+    /// Second block: the rest of the zkSync preimage, or the padded Safe CREATE2
+    /// message with the salt zeroed. Unused for plain CREATE2.
+    pub second: [u64; 25],
+    /// Byte offset within `first` of every nonce byte, with the byte's index in the
+    /// little-endian nonce.
+    pub nonce_bytes: Vec<(usize, usize)>,
+}
+
+impl MessageLanes {
+    /// Lanes of `config` for the given random segment of the salt.
+    pub fn new(config: &MinerConfig, random: &[u8]) -> Self {
+        // nonce 0 leaves the nonce bytes zeroed
+        let salt = config.salt.fill(random, 0);
+        let mut second = [0u64; 25];
+
+        let (layout, first, salt_offset) = match &config.derivation {
+            Derivation::Create2 => (
+                LaneLayout::Create2,
+                padded_block(&create2_message(config, &salt)),
+                CREATE2_SALT_OFFSET,
+            ),
+            Derivation::Safe { initializer_hash } => {
+                let mut inner = [0u8; 64];
+                inner[..32].copy_from_slice(initializer_hash);
+                inner[32..].copy_from_slice(&salt);
+                second = padded_block(&create2_message(config, &[0u8; 32]));
+                (LaneLayout::Safe, padded_block(&inner), 32)
+            }
+            Derivation::ZkSync { input_hash } => {
+                let message = zksync::message(&config.factory, &salt, &config.codehash, input_hash);
+                let rate = RATE_LANES * 8;
+                second = padded_block(&message[rate..]);
+                (LaneLayout::ZkSync, block_lanes(&message[..rate]), 64)
+            }
+        };
+
+        let nonce_bytes = config
+            .salt
+            .bytes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, byte)| match *byte {
+                SaltByte::Nonce(idx) => Some((salt_offset + i, idx)),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            layout,
+            first,
+            second,
+            nonce_bytes,
+        }
+    }
+
+    /// Keccak state after absorbing the message for `nonce`.
+    pub fn state(&self, nonce: u64) -> [u64; 25] {
+        let nonce = nonce.to_le_bytes();
+        let mut state = self.first;
+        for &(offset, idx) in &self.nonce_bytes {
+            set_byte(&mut state, offset, nonce[idx]);
+        }
+        keccak_f(&mut state);
+
+        match self.layout {
+            LaneLayout::Create2 => {}
+            LaneLayout::Safe => {
+                let salt = [state[0], state[1], state[2], state[3]];
+                state = self.second;
+                for (i, word) in salt.into_iter().enumerate() {
+                    state[SALT_LANE + i] |= word << SALT_SHIFT;
+                    state[SALT_LANE + i + 1] |= word >> (64 - SALT_SHIFT);
+                }
+                keccak_f(&mut state);
+            }
+            LaneLayout::ZkSync => {
+                for (lane, second) in state.iter_mut().zip(&self.second[..RATE_LANES]) {
+                    *lane ^= second;
+                }
+                keccak_f(&mut state);
+            }
+        }
+        state
+    }
+
+    /// Address for `nonce`, equal to [`super::compute_address`] of the filled salt.
+    pub fn address(&self, nonce: u64) -> Address {
+        Address::from_slice(&digest(&self.state(nonce))[12..])
+    }
+
+    /// Addresses for the `N` consecutive nonces starting at `nonce`, hashed side by side
+    /// with [`keccak_f_batch`].
+    pub fn addresses<const N: usize>(&self, nonce: u64) -> [Address; N] {
+        let mut state: [[u64; N]; 25] = self.first.map(|lane| [lane; N]);
+        let nonces: [[u8; 8]; N] =
+            std::array::from_fn(|j| nonce.wrapping_add(j as u64).to_le_bytes());
+        for &(offset, idx) in &self.nonce_bytes {
+            for (lane, nonce) in state[offset / 8].iter_mut().zip(&nonces) {
+                *lane |= (nonce[idx] as u64) << (8 * (offset % 8));
+            }
+        }
+        keccak_f_batch(&mut state);
+
+        match self.layout {
+            LaneLayout::Create2 => {}
+            LaneLayout::Safe => {
+                let salt = [state[0], state[1], state[2], state[3]];
+                state = self.second.map(|lane| [lane; N]);
+                for (i, words) in salt.into_iter().enumerate() {
+                    for (j, word) in words.into_iter().enumerate() {
+                        state[SALT_LANE + i][j] |= word << SALT_SHIFT;
+                        state[SALT_LANE + i + 1][j] |= word >> (64 - SALT_SHIFT);
+                    }
+                }
+                keccak_f_batch(&mut state);
+            }
+            LaneLayout::ZkSync => {
+                for (lanes, second) in state.iter_mut().zip(&self.second[..RATE_LANES]) {
+                    lanes.iter_mut().for_each(|lane| *lane ^= second);
+                }
+                keccak_f_batch(&mut state);
+            }
+        }
+
+        // the address is bytes 12..32 of the digest, the upper half of lane 1 and
+        // lanes 2 and 3
+        std::array::from_fn(|j| {
+            let mut address = Address::ZERO;
+            address[..4].copy_from_slice(&state[1][j].to_le_bytes()[4..]);
+            address[4..12].copy_from_slice(&state[2][j].to_le_bytes());
+            address[12..].copy_from_slice(&state[3][j].to_le_bytes());
+            address
+        })
+    }
+
+    /// Both blocks as the 50 lanes passed to the kernel.
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.first.iter().chain(&self.second).copied().collect()
+    }
+}
+
+/// The 85-byte CREATE2 message for the given (final) salt.
+/// Address of `salt` with the whole message assembled and padded byte by byte for this
+/// one hash, as before [`MessageLanes`]. Only the baseline of `salty bench --lanes`.
+pub fn sponge_address(config: &MinerConfig, salt: &[u8; 32]) -> Address {
+    let state = match &config.derivation {
+        Derivation::Create2 => {
+            let mut state = padded_block(&create2_message(config, salt));
+            keccak_f(&mut state);
+            state
+        }
+        Derivation::Safe { initializer_hash } => {
+            let mut inner = [0u8; 64];
+            inner[..32].copy_from_slice(initializer_hash);
+            inner[32..].copy_from_slice(salt);
+            let mut state = padded_block(&inner);
+            keccak_f(&mut state);
+
+            let mut state = padded_block(&create2_message(config, &digest(&state)));
+            keccak_f(&mut state);
+            state
+        }
+        Derivation::ZkSync { input_hash } => {
+            let message = zksync::message(&config.factory, salt, &config.codehash, input_hash);
+            let rate = RATE_LANES * 8;
+            let mut state = block_lanes(&message[..rate]);
+            keccak_f(&mut state);
+            for (lane, second) in state.iter_mut().zip(padded_block(&message[rate..])) {
+                *lane ^= second;
+            }
+            keccak_f(&mut state);
+            state
+        }
+    };
+    Address::from_slice(&digest(&state)[12..])
+}
+
+fn create2_message(config: &MinerConfig, salt: &[u8; 32]) -> [u8; 85] {
+    let mut message = [0u8; 85];
+    message[0] = 0xff;
+    message[1..21].copy_from_slice(&config.factory);
+    message[21..53].copy_from_slice(salt);
+    message[53..].copy_from_slice(&config.codehash);
+    message
+}
+
+/// OR `byte` into the state at byte `offset`, which has to be zeroed.
+fn set_byte(state: &mut [u64; 25], offset: usize, byte: u8) {
+    state[offset / 8] |= (byte as u64) << (8 * (offset % 8));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{compute_address, parse_config, RawConfig};
+    use alloy_primitives::{hex, keccak256};
+
+    fn config(derivation: &str) -> MinerConfig {
+        let mut raw = RawConfig {
+            factory: "0x4e1DCf7AD4e460CfD30791CCC4F9c8a4f820ec67".to_string(),
+            codehash: Some(format!("0x{}", hex::encode(keccak256([1u8])))),
+            worksize: 1,
+            pattern: "00".to_string(),
+            salt: Some("0xcafe,zero:4,random:10,nonce,random:8".to_string()),
+            ..Default::default()
+        };
+        match derivation {
+            "safe" => {
+                raw.derivation = Some("safe".to_string());
+                raw.singleton = Some("0x41675C099F32341bf84BFc5382aF534df5C7461a".to_string());
+                raw.initializer = Some("0xb63e800d".to_string());
+                raw.proxy_code = Some("0x6080604052".to_string());
+            }
+            "zksync" => raw.chain = Some("zksync".to_string()),
+            _ => {}
+        }
+        parse_config(raw).unwrap()
+    }
+
+    #[test]
+    fn lanes_match_compute_address() {
+        let random: Vec<u8> = (1..=18).collect();
+        for derivation in ["create2", "safe", "zksync"] {
+            let config = config(derivation);
+            let lanes = MessageLanes::new(&config, &random);
+            assert_eq!(lanes.nonce_bytes.len(), 8);
+            for nonce in [0, 1, 0x0123456789abcdef, u64::MAX] {
+                let salt = config.salt.fill(&random, nonce);
+                assert_eq!(
+                    lanes.address(nonce),
+                    compute_address(&config, &salt),
+                    "{}",
+                    derivation
+                );
+                assert_eq!(
+                    sponge_address(&config, &salt),
+                    compute_address(&config, &salt),
+                    "{}",
+                    derivation
+                );
+            }
+
+            let batch = lanes.addresses::<8>(u64::MAX - 3);
+            for (j, address) in batch.iter().enumerate() {
+                assert_eq!(
+                    *address,
+                    lanes.address((u64::MAX - 3).wrapping_add(j as u64))
+                );
+            }
+        }
+    }
+}
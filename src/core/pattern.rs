@@ -0,0 +1,161 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::strip_0x;
+
+/// Constraint on the 20 address bytes, an address matches when `address & mask == value`.
+///
+/// A hex prefix constrains whole leading bytes, other constraints such as Uniswap v4
+/// hook flags constrain individual bits anywhere in the address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub value: [u8; 20],
+    pub mask: [u8; 20],
+}
+
+impl Pattern {
+    /// Pattern matching every address.
+    pub fn any() -> Self {
+        Self {
+            value: [0; 20],
+            mask: [0; 20],
+        }
+    }
+
+    /// Pattern matching addresses starting with `bytes`.
+    pub fn prefix(bytes: &[u8]) -> Self {
+        let mut pattern = Self::any();
+        pattern.value[..bytes.len()].copy_from_slice(bytes);
+        pattern.mask[..bytes.len()].fill(0xff);
+        pattern
+    }
+
+    /// Both patterns at once, or `None` if they require different values for a bit.
+    pub fn and(&self, other: &Pattern) -> Option<Pattern> {
+        let mut combined = Self::any();
+        for i in 0..20 {
+            let shared = self.mask[i] & other.mask[i];
+            if self.value[i] & shared != other.value[i] & shared {
+                return None;
+            }
+            combined.mask[i] = self.mask[i] | other.mask[i];
+            combined.value[i] = self.value[i] | other.value[i];
+        }
+        Some(combined)
+    }
+
+    pub fn matches(&self, address: &Address) -> bool {
+        address
+            .iter()
+            .zip(self.mask.iter().zip(self.value.iter()))
+            .all(|(byte, (mask, value))| byte & mask == *value)
+    }
+
+    /// Number of address bits fixed by the pattern.
+    pub fn constrained_bits(&self) -> u32 {
+        self.mask.iter().map(|mask| mask.count_ones()).sum()
+    }
+
+    /// Number of leading bytes that are fully constrained.
+    pub fn prefix_len(&self) -> usize {
+        self.mask.iter().take_while(|&&mask| mask == 0xff).count()
+    }
+}
+
+/// Parse a hex string of any length (e.g., 'c0ffee' or 'abc') into its nibbles.
+pub fn parse_nibbles(input: &str) -> Result<Vec<u8>, String> {
+    let nibbles = strip_0x(input)
+        .chars()
+        .map(|c| c.to_digit(16).map(|nibble| nibble as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("Invalid hex pattern provided: '{}'.", input))?;
+    if nibbles.is_empty() {
+        return Err("Pattern cannot be empty.".to_string());
+    }
+    if nibbles.len() > 40 {
+        return Err(format!(
+            "Pattern is too long ({} nibbles). Maximum address length is 40 nibbles.",
+            nibbles.len()
+        ));
+    }
+    Ok(nibbles)
+}
+
+/// Nibble offset of the first occurrence of `nibbles` anywhere in `address`.
+pub fn find_nibbles(nibbles: &[u8], address: &Address) -> Option<usize> {
+    let haystack: Vec<u8> = address
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xf])
+        .collect();
+    haystack
+        .windows(nibbles.len())
+        .position(|window| window == nibbles)
+}
+
+/// Hex nibbles of the constrained part, with `.` for nibbles that can take any value.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nibbles: String = (0..40)
+            .map(|i| {
+                let shift = if i % 2 == 0 { 4 } else { 0 };
+                let mask = (self.mask[i / 2] >> shift) & 0xf;
+                let value = (self.value[i / 2] >> shift) & 0xf;
+                if mask == 0 {
+                    '.'
+                } else {
+                    char::from_digit(value as u32, 16).unwrap()
+                }
+            })
+            .collect();
+        write!(f, "0x{}", nibbles.trim_end_matches('.'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_matches_leading_bytes() {
+        let pattern = Pattern::prefix(&[0xde, 0xad]);
+        let mut address = Address::repeat_byte(0x11);
+        assert!(!pattern.matches(&address));
+        address[0] = 0xde;
+        address[1] = 0xad;
+        assert!(pattern.matches(&address));
+        assert_eq!(pattern.constrained_bits(), 16);
+        assert_eq!(pattern.prefix_len(), 2);
+        assert_eq!(pattern.to_string(), "0xdead");
+    }
+
+    #[test]
+    fn finds_nibbles_at_odd_offsets() {
+        let nibbles = parse_nibbles("0xc0ffee").unwrap();
+        assert_eq!(nibbles, [0xc, 0x0, 0xf, 0xf, 0xe, 0xe]);
+
+        let address: Address = "0x1111111111111111111111111c0ffee111111111"
+            .parse()
+            .unwrap();
+        assert_eq!(find_nibbles(&nibbles, &address), Some(25));
+        assert_eq!(find_nibbles(&nibbles, &Address::ZERO), None);
+
+        assert!(parse_nibbles("").is_err());
+        assert!(parse_nibbles("c0fg").is_err());
+        assert!(parse_nibbles(&"0".repeat(41)).is_err());
+    }
+
+    #[test]
+    fn and_detects_conflicts() {
+        let mut low = Pattern::any();
+        low.mask[19] = 0x0f;
+        low.value[19] = 0x05;
+
+        let combined = Pattern::prefix(&[0xaa]).and(&low).unwrap();
+        assert_eq!(combined.constrained_bits(), 12);
+        assert_eq!(combined.to_string(), format!("0xaa{}5", ".".repeat(37)));
+
+        let conflicting = Pattern::prefix(&[0; 20]);
+        assert!(conflicting.and(&low).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use alloy_primitives::{hex, keccak256};
+
+use super::strip_0x;
+
+/// Salt template used for Safe deployments unless configured otherwise. The mined salt
+/// is the `saltNonce`, so the caller does not apply and the nonce fits in 96 bits.
+pub const DEFAULT_SAFE_SALT_TEMPLATE: &str = "zero:20,random:4,nonce";
+
+/// Inputs of `SafeProxyFactory.createProxyWithNonce(singleton, initializer, saltNonce)`.
+///
+/// `proxy_code` is the proxy creation code of the factory, as returned by its
+/// `proxyCreationCode()` view, which differs between Safe releases.
+pub struct SafeDeployment {
+    pub singleton: [u8; 20],
+    pub initializer: Vec<u8>,
+    pub proxy_code: Vec<u8>,
+}
+
+impl SafeDeployment {
+    pub fn parse(singleton: &str, initializer: &str, proxy_code: &str) -> Result<Self, String> {
+        let singleton = super::parse_fixed_hex::<20>(singleton, "singleton")?;
+        let initializer = hex::decode(strip_0x(initializer))
+            .map_err(|_| format!("Invalid hex string for initializer: '{}'.", initializer))?;
+        let proxy_code = hex::decode(strip_0x(proxy_code))
+            .ok()
+            .filter(|code| !code.is_empty())
+            .ok_or_else(|| format!("Invalid hex string for proxy code: '{}'.", proxy_code))?;
+
+        Ok(Self {
+            singleton,
+            initializer,
+            proxy_code,
+        })
+    }
+
+    /// Keccak-256 of the proxy init code, the creation code followed by the singleton
+    /// address as a 32-byte constructor argument.
+    pub fn codehash(&self) -> [u8; 32] {
+        let mut init_code = self.proxy_code.clone();
+        init_code.extend_from_slice(&[0u8; 12]);
+        init_code.extend_from_slice(&self.singleton);
+        keccak256(init_code).0
+    }
+
+    pub fn initializer_hash(&self) -> [u8; 32] {
+        keccak256(&self.initializer).0
+    }
+}
+
+/// The CREATE2 salt used by the proxy factory, `keccak256(keccak256(initializer) ++ saltNonce)`.
+pub fn create2_salt(initializer_hash: &[u8; 32], salt_nonce: &[u8; 32]) -> [u8; 32] {
+    let mut message = [0u8; 64];
+    message[..32].copy_from_slice(initializer_hash);
+    message[32..].copy_from_slice(salt_nonce);
+    keccak256(message).0
+}
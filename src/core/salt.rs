@@ -0,0 +1,205 @@
+use alloy_primitives::hex;
+use serde::{Deserialize, Serialize};
+
+use super::strip_0x;
+
+/// Layout used when no salt template is configured, matching the original scheme of
+/// the 20-byte caller (to prevent frontrunning), 4 random bytes (to prevent collisions
+/// with other runs) and an 8-byte nonce stepped through during the run.
+pub const DEFAULT_SALT_TEMPLATE: &str = "caller,random:4,nonce";
+
+/// Named layouts accepted in place of a template.
+///
+/// `zero-caller` leaves the caller bytes zeroed, which factories such as
+/// ImmutableCreate2Factory accept from any sender, so the salt can be used by any
+/// deployer. `mined` also mines the first 20 bytes, for factories that do not restrict
+/// the salt at all.
+pub const SALT_PRESETS: [(&str, &str); 3] = [
+    ("default", DEFAULT_SALT_TEMPLATE),
+    ("zero-caller", "zero:20,random:4,nonce"),
+    ("mined", "random:24,nonce"),
+];
+
+/// Number of salt bytes taken by the `nonce` region.
+pub const NONCE_LEN: usize = 8;
+
+/// Source of a single byte of the 32-byte salt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaltByte {
+    /// A constant byte (fixed prefix, caller or zero region).
+    Fixed(u8),
+    /// Byte `n` of the random segment, chosen whenever a new salt prefix is picked.
+    Random(usize),
+    /// Byte `n` of the little-endian 64-bit nonce.
+    Nonce(usize),
+}
+
+/// The 32-byte salt described region by region, e.g. `0xcafe,zero:18,random:4,nonce`.
+///
+/// Supported regions are `caller` (the 20-byte caller address), `0x<hex>` or
+/// `text:<ascii>` (fixed bytes), `zero:<n>`, `random:<n>` and exactly one `nonce`
+/// (8 bytes). The regions must add up to 32 bytes. One of the [`SALT_PRESETS`] names
+/// can be used instead of a list of regions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaltTemplate {
+    pub bytes: [SaltByte; 32],
+}
+
+impl SaltTemplate {
+    pub fn parse(input: &str, caller: Option<&[u8; 20]>) -> Result<Self, String> {
+        let input = SALT_PRESETS
+            .iter()
+            .find(|(name, _)| *name == input.trim())
+            .map_or(input, |(_, template)| *template);
+
+        let mut bytes = Vec::with_capacity(32);
+        let mut random_len = 0;
+        let mut has_nonce = false;
+
+        for region in input.split(',').map(str::trim) {
+            let (name, arg) = match region.split_once(':') {
+                Some((name, arg)) => (name.trim(), Some(arg.trim())),
+                None => (region, None),
+            };
+
+            match (name, arg) {
+                ("caller", None) => {
+                    let caller = caller.ok_or_else(|| {
+                        "Salt template uses the caller, but no caller was provided. Use the \
+                         'zero-caller' salt template for permissionless salts."
+                            .to_string()
+                    })?;
+                    bytes.extend(caller.iter().map(|&b| SaltByte::Fixed(b)));
+                }
+                ("nonce", None) => {
+                    if has_nonce {
+                        return Err("Salt template can only contain one nonce region.".to_string());
+                    }
+                    has_nonce = true;
+                    bytes.extend((0..NONCE_LEN).map(SaltByte::Nonce));
+                }
+                ("zero", Some(len)) => {
+                    let len = parse_region_len(len, region)?;
+                    bytes.extend((0..len).map(|_| SaltByte::Fixed(0)));
+                }
+                ("random", Some(len)) => {
+                    let len = parse_region_len(len, region)?;
+                    bytes.extend((random_len..random_len + len).map(SaltByte::Random));
+                    random_len += len;
+                }
+                ("text", Some(text)) if text.is_ascii() && !text.is_empty() => {
+                    bytes.extend(text.bytes().map(SaltByte::Fixed));
+                }
+                (fixed, None) if fixed.starts_with("0x") || fixed.starts_with("0X") => {
+                    let data = hex::decode(strip_0x(fixed))
+                        .ok()
+                        .filter(|data| !data.is_empty())
+                        .ok_or_else(|| format!("Invalid fixed salt bytes: '{}'.", fixed))?;
+                    bytes.extend(data.into_iter().map(SaltByte::Fixed));
+                }
+                _ => return Err(format!("Invalid salt template region: '{}'.", region)),
+            }
+
+            if bytes.len() > 32 {
+                return Err(format!(
+                    "Salt template '{}' is longer than 32 bytes.",
+                    input
+                ));
+            }
+        }
+
+        if !has_nonce {
+            return Err("Salt template must contain a nonce region.".to_string());
+        }
+        if bytes.len() != 32 {
+            return Err(format!(
+                "Salt template '{}' is {} bytes long, expected 32 bytes.",
+                input,
+                bytes.len()
+            ));
+        }
+
+        let mut template = [SaltByte::Fixed(0); 32];
+        template.copy_from_slice(&bytes);
+        Ok(Self { bytes: template })
+    }
+
+    /// Number of random bytes that have to be supplied to [`SaltTemplate::fill`].
+    pub fn random_len(&self) -> usize {
+        self.bytes
+            .iter()
+            .filter(|b| matches!(b, SaltByte::Random(_)))
+            .count()
+    }
+
+    /// Build the salt for the given random segment and nonce.
+    pub fn fill(&self, random: &[u8], nonce: u64) -> [u8; 32] {
+        let nonce = nonce.to_le_bytes();
+        let mut salt = [0u8; 32];
+        for (out, byte) in salt.iter_mut().zip(self.bytes.iter()) {
+            *out = match *byte {
+                SaltByte::Fixed(value) => value,
+                SaltByte::Random(idx) => random[idx],
+                SaltByte::Nonce(idx) => nonce[idx],
+            };
+        }
+        salt
+    }
+}
+
+fn parse_region_len(input: &str, region: &str) -> Result<usize, String> {
+    match input.parse::<usize>() {
+        Ok(len) if len > 0 => Ok(len),
+        _ => Err(format!(
+            "Invalid length in salt template region: '{}'.",
+            region
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template_matches_original_layout() {
+        let caller = [0xaa; 20];
+        let template = SaltTemplate::parse(DEFAULT_SALT_TEMPLATE, Some(&caller)).unwrap();
+        assert_eq!(template.random_len(), 4);
+
+        let salt = template.fill(&[1, 2, 3, 4], 0x0807060504030201);
+        assert_eq!(&salt[..20], &caller);
+        assert_eq!(&salt[20..24], &[1, 2, 3, 4]);
+        assert_eq!(&salt[24..], &[1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn parses_fixed_and_text_regions() {
+        let template = SaltTemplate::parse("text:salty,0xcafe,zero:17,nonce", None).unwrap();
+        let salt = template.fill(&[], 0);
+        assert_eq!(&salt[..5], b"salty");
+        assert_eq!(&salt[5..7], &[0xca, 0xfe]);
+        assert_eq!(template.random_len(), 0);
+    }
+
+    #[test]
+    fn permissionless_presets_do_not_need_caller() {
+        let zero = SaltTemplate::parse("zero-caller", None).unwrap();
+        assert_eq!(&zero.fill(&[0xff; 4], u64::MAX)[..20], &[0; 20]);
+
+        let mined = SaltTemplate::parse("mined", None).unwrap();
+        assert_eq!(mined.random_len(), 24);
+
+        assert!(SaltTemplate::parse("default", None).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let caller = Some(&[0; 20]);
+        assert!(SaltTemplate::parse("caller,random:4", caller).is_err());
+        assert!(SaltTemplate::parse("caller,random:5,nonce", caller).is_err());
+        assert!(SaltTemplate::parse("caller,nonce,nonce", caller).is_err());
+        assert!(SaltTemplate::parse("caller,random:0,random:4,nonce", caller).is_err());
+        assert!(SaltTemplate::parse("caller,bogus:4,nonce", caller).is_err());
+    }
+}
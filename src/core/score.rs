@@ -0,0 +1,183 @@
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// Calldata gas saved by every zero byte of an address passed as an argument, as a
+/// zero byte costs 4 gas instead of 16 (EIP-2028).
+pub const ZERO_BYTE_GAS_SAVING: u32 = 12;
+
+/// How matching addresses are ranked against each other. In a ranking mode only
+/// addresses reaching the minimum score are reported, and after each hit the minimum
+/// is raised so that only improvements follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ranking {
+    /// Every address matching the pattern is reported.
+    None,
+    /// Total number of zero bytes anywhere in the address, ties broken by the number
+    /// of zero nibbles.
+    ZeroBytes,
+    /// Number of leading nibbles equal to the first one, e.g. 7 for `0x7777777a...`.
+    RepeatedNibbles,
+    /// Number of leading bytes equal to the first one, e.g. 3 for `0xc0c0c0...`.
+    RepeatedBytes,
+}
+
+impl Ranking {
+    pub fn parse(input: Option<&str>) -> Result<Self, String> {
+        match input {
+            None | Some("none") => Ok(Ranking::None),
+            Some("zero-bytes") => Ok(Ranking::ZeroBytes),
+            Some("repeated-nibbles") => Ok(Ranking::RepeatedNibbles),
+            Some("repeated-bytes") => Ok(Ranking::RepeatedBytes),
+            Some(other) => Err(format!("Unknown ranking: '{}'.", other)),
+        }
+    }
+
+    /// Score of `address`, higher is better.
+    pub fn score(&self, address: &Address) -> u32 {
+        match self {
+            Ranking::None => 0,
+            // the nibble count (at most 40) fits below the byte count
+            Ranking::ZeroBytes => (zero_bytes(address) << 6) | zero_nibbles(address),
+            Ranking::RepeatedNibbles => repeated_nibbles(address),
+            Ranking::RepeatedBytes => repeated_bytes(address),
+        }
+    }
+
+    /// Score needed to reach `min` in the unit shown to the user (e.g. zero bytes).
+    pub fn min_score(&self, min: u32) -> u32 {
+        match self {
+            Ranking::None => 0,
+            Ranking::ZeroBytes => min << 6,
+            Ranking::RepeatedNibbles | Ranking::RepeatedBytes => min,
+        }
+    }
+
+    /// `min_score` as encoded by [`Ranking::min_score`], back in the unit shown to the user.
+    pub fn display_min_score(&self, min_score: u32) -> u32 {
+        match self {
+            Ranking::None => 0,
+            Ranking::ZeroBytes => min_score >> 6,
+            Ranking::RepeatedNibbles | Ranking::RepeatedBytes => min_score,
+        }
+    }
+
+    /// Human readable score of `address`, `None` without a ranking.
+    pub fn describe(&self, address: &Address) -> Option<String> {
+        match self {
+            Ranking::None => None,
+            Ranking::ZeroBytes => {
+                let bytes = zero_bytes(address);
+                Some(format!(
+                    "{} zero bytes, {} zero nibbles, ~{} gas saved per call",
+                    bytes,
+                    zero_nibbles(address),
+                    bytes * ZERO_BYTE_GAS_SAVING
+                ))
+            }
+            Ranking::RepeatedNibbles => Some(format!(
+                "{} repeated leading nibbles",
+                repeated_nibbles(address)
+            )),
+            Ranking::RepeatedBytes => Some(format!(
+                "{} repeated leading bytes",
+                repeated_bytes(address)
+            )),
+        }
+    }
+
+    /// Probability that a random address reaches `min_score`.
+    pub fn probability(&self, min_score: u32) -> f64 {
+        match self {
+            Ranking::None => 1.0,
+            Ranking::ZeroBytes => {
+                // binomial tail of at least `min` zero bytes, each with probability 1/256
+                let min = (min_score >> 6).min(20);
+                let p = 1.0f64 / 256.0;
+                (min..=20)
+                    .map(|k| binomial(20, k) * p.powi(k as i32) * (1.0 - p).powi(20 - k as i32))
+                    .sum()
+            }
+            // the first nibble or byte is free, every further one has to repeat it
+            Ranking::RepeatedNibbles => (1.0f64 / 16.0).powi(min_score.clamp(1, 40) as i32 - 1),
+            Ranking::RepeatedBytes => (1.0f64 / 256.0).powi(min_score.clamp(1, 20) as i32 - 1),
+        }
+    }
+}
+
+pub fn zero_bytes(address: &Address) -> u32 {
+    address.iter().filter(|&&byte| byte == 0).count() as u32
+}
+
+pub fn zero_nibbles(address: &Address) -> u32 {
+    address
+        .iter()
+        .map(|byte| (byte >> 4 == 0) as u32 + (byte & 0xf == 0) as u32)
+        .sum()
+}
+
+pub fn repeated_nibbles(address: &Address) -> u32 {
+    let first = address[0] >> 4;
+    address
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xf])
+        .take_while(|&nibble| nibble == first)
+        .count() as u32
+}
+
+pub fn repeated_bytes(address: &Address) -> u32 {
+    address
+        .iter()
+        .take_while(|&&byte| byte == address[0])
+        .count() as u32
+}
+
+fn binomial(n: u32, k: u32) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn zero_bytes_score_breaks_ties_by_nibbles() {
+        let a = address!("00a0000b00000000000000000000000000c0d0e0");
+        assert_eq!(zero_bytes(&a), 15);
+        assert_eq!(zero_nibbles(&a), 15 * 2 + 5);
+
+        let b = address!("00a0000b00000000000000000000000000c0d0e1");
+        assert!(Ranking::ZeroBytes.score(&a) > Ranking::ZeroBytes.score(&b));
+        assert!(Ranking::ZeroBytes.score(&a) >= Ranking::ZeroBytes.min_score(15));
+        assert!(Ranking::ZeroBytes.score(&a) < Ranking::ZeroBytes.min_score(16));
+        let min_score = Ranking::ZeroBytes.min_score(6);
+        assert_eq!(Ranking::ZeroBytes.display_min_score(min_score), 6);
+        assert_eq!(
+            Ranking::ZeroBytes.describe(&a).unwrap(),
+            "15 zero bytes, 35 zero nibbles, ~180 gas saved per call"
+        );
+    }
+
+    #[test]
+    fn repeated_leading_runs() {
+        let a = address!("7777777a00000000000000000000000000000000");
+        assert_eq!(repeated_nibbles(&a), 7);
+        assert_eq!(repeated_bytes(&a), 3);
+
+        let b = address!("c0c0c0c1ffffffffffffffffffffffffffffffff");
+        assert_eq!(repeated_nibbles(&b), 1);
+        assert_eq!(repeated_bytes(&b), 3);
+
+        let c = Address::repeat_byte(0x55);
+        assert_eq!(repeated_nibbles(&c), 40);
+        assert_eq!(repeated_bytes(&c), 20);
+        assert_eq!(Ranking::RepeatedNibbles.probability(2), 1.0 / 16.0);
+    }
+
+    #[test]
+    fn zero_bytes_probability() {
+        assert_eq!(Ranking::ZeroBytes.probability(0), 1.0);
+        let one = Ranking::ZeroBytes.probability(Ranking::ZeroBytes.min_score(1));
+        assert!((one - (1.0 - (255.0f64 / 256.0).powi(20))).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,187 @@
+use alloy_primitives::{address, hex, keccak256, Address};
+
+use super::{
+    compute_address,
+    keyspace::Keyspace,
+    lanes::MessageLanes,
+    parse_config,
+    pattern::{find_nibbles, Pattern},
+    run_batch, MinerConfig, RawConfig,
+};
+
+/// Address of example 4 of EIP-1014: deployer `0x00000000000000000000000000000000deadbeef`,
+/// salt `0x00000000000000000000000000000000000000000000000000000000cafebabe` and init
+/// code `0xdeadbeef`.
+pub const KNOWN_ADDRESS: Address = address!("60f3f640a8508fC6a86d45DF051962668E1e8AC7");
+
+/// Nonce of the salt of [`KNOWN_ADDRESS`] in the salt template `zero:24,nonce`, whose
+/// nonce bytes are little-endian.
+pub const KNOWN_NONCE: u64 = 0xbeba_feca_0000_0000;
+
+/// The deployment of [`KNOWN_ADDRESS`] as a mining target for `pattern`.
+pub fn known_config(pattern: Pattern) -> MinerConfig {
+    let mut config = parse_config(RawConfig {
+        factory: "0x00000000000000000000000000000000deadbeef".to_string(),
+        codehash: Some(format!("0x{}", hex::encode(keccak256(hex!("deadbeef"))))),
+        worksize: 1,
+        pattern: "00".to_string(),
+        salt: Some("zero:24,nonce".to_string()),
+        ..Default::default()
+    })
+    .expect("the known-answer target is valid");
+    config.pattern = pattern;
+    config
+}
+
+/// Check the CPU search used by the WASM miner, and to verify the hits of the OpenCL
+/// miner, before a run: the known salt has to hash to [`KNOWN_ADDRESS`] on every path,
+/// and a pattern matching any address has to be reported as a hit.
+pub fn check_cpu() -> Result<(), String> {
+    let config = known_config(Pattern::prefix(KNOWN_ADDRESS.as_slice()));
+    let salt = config.salt.fill(&[], KNOWN_NONCE);
+    let lanes = MessageLanes::new(&config, &[]);
+
+    let mut addresses = vec![
+        ("compute_address", compute_address(&config, &salt)),
+        ("the message lanes", lanes.address(KNOWN_NONCE)),
+    ];
+    // the known salt in every position of a batch
+    for j in 0..8 {
+        let batch = lanes.addresses::<8>(KNOWN_NONCE - j as u64);
+        addresses.push(("the batched Keccak-f", batch[j]));
+    }
+    for (path, address) in addresses {
+        if address != KNOWN_ADDRESS {
+            return Err(format!(
+                "{} hashed the salt 0x{} to {}, expected {}",
+                path,
+                hex::encode(salt),
+                address,
+                KNOWN_ADDRESS
+            ));
+        }
+    }
+
+    // a pattern without constrained bits forces a hit on the first salt
+    let config = known_config(Pattern::any());
+    let (found, _) = run_batch(&config, &Keyspace::new(&config, 0, 1, 8), 0, 0);
+    let hit = found
+        .first()
+        .ok_or("the CPU search reported no hit for a pattern matching any address")?;
+    let salt: [u8; 32] = hex::decode(&hit.salt)
+        .ok()
+        .and_then(|salt| salt.try_into().ok())
+        .ok_or_else(|| format!("the CPU search reported the invalid salt {}", hit.salt))?;
+    let address = compute_address(&config, &salt);
+    if hit.address.parse::<Address>() != Ok(address) {
+        return Err(format!(
+            "the CPU search reported {} for the salt {}, expected {}",
+            hit.address, hit.salt, address
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether `address` meets the pattern, `contains` nibbles, pattern expression and
+/// minimum score of `config`, as checked on the host for the hits of every backend.
+pub fn is_hit(config: &MinerConfig, address: &Address) -> bool {
+    let contained = config
+        .contains
+        .as_ref()
+        .is_none_or(|nibbles| find_nibbles(nibbles, address).is_some());
+    let expressed = config
+        .expression
+        .as_ref()
+        .is_none_or(|expression| expression.find(address).is_some());
+
+    config.pattern.matches(address)
+        && contained
+        && expressed
+        && config.ranking.score(address) >= config.min_score
+}
+
+/// Addresses built to meet the constraints of `config` without a search, to force a
+/// hit through a search whose constraints are compiled in: the fixed bits of the
+/// pattern with all other bits cleared or set, and with the `contains` nibbles at the
+/// first position the pattern leaves free. Which of them are hits is up to [`is_hit`];
+/// pattern expressions are not taken into account.
+pub fn hit_candidates(config: &MinerConfig) -> Vec<Address> {
+    let Pattern { value, mask } = config.pattern;
+    let set: [u8; 20] = std::array::from_fn(|i| value[i] | !mask[i]);
+    let mut candidates = vec![Address::from(value), Address::from(set)];
+
+    if let Some(nibbles) = &config.contains {
+        for position in 0..=40 - nibbles.len() {
+            let mut bytes = value;
+            for (i, &nibble) in nibbles.iter().enumerate() {
+                let n = position + i;
+                let shift = if n % 2 == 0 { 4 } else { 0 };
+                bytes[n / 2] = (bytes[n / 2] & !(0xf << shift)) | (nibble << shift);
+            }
+            let address = Address::from(bytes);
+            if config.pattern.matches(&address) {
+                candidates.push(address);
+                break;
+            }
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_passes_the_self_test() {
+        assert_eq!(check_cpu(), Ok(()));
+    }
+
+    #[test]
+    fn hit_candidates_force_a_hit() {
+        let config = |pattern: &str, configure: fn(&mut RawConfig)| {
+            let mut raw = RawConfig {
+                factory: "0x00000000000000000000000000000000deadbeef".to_string(),
+                codehash: Some(format!("0x{}", "11".repeat(32))),
+                worksize: 1,
+                pattern: pattern.to_string(),
+                salt: Some("zero-caller".to_string()),
+                ..Default::default()
+            };
+            configure(&mut raw);
+            parse_config(raw).unwrap()
+        };
+        let configs = [
+            config("deadbeef", |_| {}),
+            config("c0ffee", |raw| {
+                raw.match_mode = Some("contains".to_string())
+            }),
+            config("", |raw| {
+                raw.hooks = Some("beforeSwap,afterSwap".to_string())
+            }),
+            config("", |raw| {
+                raw.ranking = Some("zero-bytes".to_string());
+                raw.min_score = Some(4);
+            }),
+            config("", |raw| raw.ranking = Some("repeated-nibbles".to_string())),
+        ];
+
+        for config in configs {
+            let candidates = hit_candidates(&config);
+            assert!(
+                candidates.iter().any(|address| is_hit(&config, address)),
+                "{}",
+                config.pattern_label()
+            );
+        }
+    }
+
+    #[test]
+    fn known_nonce_fills_the_eip1014_salt() {
+        let config = known_config(Pattern::any());
+        let salt = config.salt.fill(&[], KNOWN_NONCE);
+        assert_eq!(hex::encode(salt), format!("{}cafebabe", "00".repeat(28)));
+    }
+}
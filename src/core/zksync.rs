@@ -0,0 +1,54 @@
+use alloy_primitives::{b256, hex, keccak256, B256};
+
+use super::strip_0x;
+
+/// `keccak256("zksyncCreate2")`, prepended to every zkSync Era CREATE2 preimage.
+pub const CREATE2_PREFIX: B256 =
+    b256!("2020dba91b30cc0006188af794c2fb30dd8520db7e2c088b7fc7c103c00ca494");
+
+/// Length of the CREATE2 preimage: prefix, padded sender, salt, bytecode hash and
+/// constructor input hash, 32 bytes each.
+pub const MESSAGE_LEN: usize = 160;
+
+/// Hash of the constructor input, which zkSync Era includes in the CREATE2 preimage.
+pub fn constructor_input_hash(input: Option<&str>) -> Result<[u8; 32], String> {
+    let input = match input {
+        Some(input) => hex::decode(strip_0x(input))
+            .map_err(|_| format!("Invalid hex string for constructor input: '{}'.", input))?,
+        None => vec![],
+    };
+    Ok(keccak256(input).0)
+}
+
+/// The zkSync Era CREATE2 preimage,
+/// `CREATE2_PREFIX ++ sender ++ salt ++ bytecodeHash ++ keccak256(constructorInput)`.
+pub fn message(
+    sender: &[u8; 20],
+    salt: &[u8; 32],
+    bytecode_hash: &[u8; 32],
+    input_hash: &[u8; 32],
+) -> [u8; MESSAGE_LEN] {
+    let mut message = [0u8; MESSAGE_LEN];
+    message[..32].copy_from_slice(CREATE2_PREFIX.as_slice());
+    message[44..64].copy_from_slice(sender);
+    message[64..96].copy_from_slice(salt);
+    message[96..128].copy_from_slice(bytecode_hash);
+    message[128..].copy_from_slice(input_hash);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create2_prefix_is_hash_of_tag() {
+        assert_eq!(keccak256("zksyncCreate2"), CREATE2_PREFIX);
+    }
+
+    #[test]
+    fn empty_constructor_input_hash() {
+        assert_eq!(constructor_input_hash(None).unwrap(), keccak256([]).0);
+        assert!(constructor_input_hash(Some("0xzz")).is_err());
+    }
+}
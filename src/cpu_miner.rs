@@ -0,0 +1,151 @@
+use alloy_primitives::Address;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Display;
+use salty::core::{
+    keyspace::Keyspace,
+    lanes::{sponge_address, MessageLanes},
+    run_batch, FoundResult, MinerConfig,
+};
+
+/// Number of salts each thread searches per batch.
+const CPU_BATCH: u32 = 0x10000;
+
+/// Search for salts of `config` on `threads` CPU threads, without an OpenCL device.
+///
+/// Every thread is a worker of the [`Keyspace`] seeded with `seed` and searches its
+/// batches with [`run_batch`], which hashes 8 salts at a time with AVX-512 or AVX2 where
+/// the CPU supports it. Matches are displayed as by the OpenCL miner; in a ranking mode,
+/// only addresses scoring higher than the best one so far are reported.
+pub fn start_cpu_miner(config: MinerConfig, display: Display, seed: u64, threads: u32) {
+    println!("Preparing CPU Miner ({} threads)...", threads);
+    println!("Seed: {} (pass --seed {} to repeat this run)", seed, seed);
+
+    let config = Arc::new(config);
+    let keyspace = Arc::new(Keyspace::new(&config, seed, threads, CPU_BATCH));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let (sender, receiver) = mpsc::channel::<FoundResult>();
+
+    for worker in 0..threads {
+        let config = config.clone();
+        let keyspace = keyspace.clone();
+        let attempts = attempts.clone();
+        let sender = sender.clone();
+
+        thread::spawn(move || {
+            for batch in 0.. {
+                let (found, tried) = run_batch(&config, &keyspace, worker, batch);
+                if tried == 0 {
+                    break;
+                }
+                attempts.fetch_add(tried as u64, Ordering::Relaxed);
+
+                for found in found {
+                    if sender.send(found).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+    // the channel disconnects once every worker has run out of salts
+    drop(sender);
+
+    let mut found_list: Vec<String> = vec![];
+
+    // in a ranking mode, raised past the best score found so far after every hit
+    let mut threshold = config.min_score;
+
+    display.start();
+
+    let mut previous_update = Instant::now();
+
+    loop {
+        match receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(found) => {
+                if let Some(output) = report(&config, &found, &mut threshold) {
+                    found_list.push(output);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                println!("Searched the whole keyspace of the salt template.");
+                return;
+            }
+        }
+
+        // we don't want to print too fast
+        if previous_update.elapsed() >= Duration::from_secs(1) {
+            previous_update = Instant::now();
+
+            // the number of attempts made so far, in millions
+            let work_rate = attempts.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+            display.update(work_rate, &config.pattern_label(), &found_list);
+        }
+    }
+}
+
+/// The line displayed for `found`, `None` if it does not beat `threshold`, which is raised
+/// past its score in a ranking mode.
+fn report(config: &MinerConfig, found: &FoundResult, threshold: &mut u32) -> Option<String> {
+    // the workers rank against their own best, keep only overall improvements
+    let address: Address = found.address.parse().unwrap();
+    let score = config.ranking.score(&address);
+    if score < *threshold {
+        return None;
+    }
+
+    let mut output = format!(
+        "{} => {} (Pattern: {})",
+        found.salt, found.address, found.pattern
+    );
+    if let Some(position) = found.position {
+        let matched = if config.expression.is_some() {
+            "Expression"
+        } else {
+            "Contains"
+        };
+        write!(output, " ({} at nibble {})", matched, position).unwrap();
+    }
+    if let Some(description) = &found.score {
+        write!(output, " ({})", description).unwrap();
+        *threshold = score + 1;
+    }
+
+    Some(output)
+}
+
+/// Rates of one CPU thread in million attempts per second over `duration` each, first
+/// assembling every message byte by byte with [`sponge_address`], then hashing from the
+/// precomputed [`MessageLanes`].
+pub fn benchmark_lanes(config: &MinerConfig, duration: Duration) -> (f64, f64) {
+    let random = Keyspace::new(config, rand::random(), 1, CPU_BATCH).random(0);
+
+    let bytewise = hash_rate(duration, |nonce| {
+        sponge_address(config, &config.salt.fill(&random, nonce))
+    });
+    let lanes = MessageLanes::new(config, &random);
+    let precomputed = hash_rate(duration, |nonce| lanes.address(nonce));
+
+    (bytewise, precomputed)
+}
+
+/// Rate of `hash` over consecutive nonces in million per second, measured for `duration`.
+fn hash_rate(duration: Duration, hash: impl Fn(u64) -> Address) -> f64 {
+    let start = Instant::now();
+    let mut nonce: u64 = 0;
+    while start.elapsed() < duration {
+        // reading the clock after every hash would skew the measurement
+        for _ in 0..0x1000 {
+            black_box(hash(black_box(nonce)));
+            nonce += 1;
+        }
+    }
+
+    nonce as f64 / start.elapsed().as_secs_f64() / 1_000_000.0
+}
@@ -1,11 +1,14 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 use console::Term;
-use indicatif::{HumanDuration, HumanFloatCount, MultiProgress, ProgressBar, ProgressStyle};
+use indicatif::{
+    HumanCount, HumanDuration, HumanFloatCount, MultiProgress, ProgressBar, ProgressStyle,
+};
+use salty::core::{difficulty, score::Ranking, MinerConfig};
 
 pub struct Display {
     // Extras
-    start_time: u64,
+    start_time: Instant,
     term: Term,
 
     // Progress Bars
@@ -19,6 +22,12 @@ struct ProgressManagers {
     target: ProgressBar,
 }
 
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display {
     pub fn new() -> Self {
         let mp = MultiProgress::new();
@@ -29,10 +38,7 @@ impl Display {
         };
 
         Self {
-            start_time: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            start_time: Instant::now(),
             term: Term::stdout(),
             mp,
             pb,
@@ -59,37 +65,90 @@ impl Display {
         self.term.clear_screen().unwrap();
     }
 
-    pub fn update(&self, work_rate: u128, pattern_len: usize, found_salts: &Vec<String>) {
-        println!("{:?}", self.start_time);
-
-        let total_runtime = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            - self.start_time;
+    pub fn update(&self, work_rate: f64, pattern: &str, found_salts: &Vec<String>) {
+        let total_runtime = self.start_time.elapsed();
 
-        if total_runtime != 0 {
+        if total_runtime.as_secs() != 0 {
             self.term.clear_last_lines(3 + found_salts.len()).unwrap();
         }
 
-        if total_runtime != 0 {
+        if total_runtime.as_secs() != 0 {
             self.pb.time.set_message(format!(
                 "Total Runtime: {}",
-                HumanDuration(Duration::from_secs(total_runtime)),
+                HumanDuration(Duration::from_secs(total_runtime.as_secs())),
             ));
 
             self.pb.speed.set_message(format!(
                 "Speed: {:.2} million attempts per second",
-                HumanFloatCount(work_rate as f64 / total_runtime as f64),
+                HumanFloatCount(work_rate / total_runtime.as_secs_f64()),
             ));
 
-            self.pb
-                .target
-                .set_message(format!("Pattern Length: {} bytes", pattern_len,));
+            self.pb.target.set_message(format!("Pattern: {}", pattern));
         }
 
         for found_salt in found_salts {
-            self.mp.println(format!("{}", found_salt)).unwrap();
+            self.mp.println(found_salt).unwrap();
         }
     }
 }
+
+/// Print the difficulty of `config` along with the attempts and time needed to reach
+/// each of the reported confidence levels at `rate` million attempts per second.
+pub fn print_estimate(config: &MinerConfig, rate: f64, benchmarked: bool) {
+    let probability = difficulty::match_probability(config);
+    let attempts_per_second = rate * 1_000_000.0;
+
+    println!("Pattern: {}", config.pattern_label());
+    if config.ranking != Ranking::None {
+        println!(
+            "Ranking: {:?} (minimum score {})",
+            config.ranking,
+            config.ranking.display_min_score(config.min_score)
+        );
+    }
+    println!("Probability per attempt: {:.3e}", probability);
+    println!(
+        "Expected attempts: {}",
+        format_count(difficulty::expected_attempts(probability))
+    );
+    println!(
+        "Speed: {:.2} million attempts per second ({})",
+        HumanFloatCount(rate),
+        if benchmarked {
+            "benchmarked"
+        } else {
+            "provided"
+        }
+    );
+    println!();
+    println!("{:<12}{:<28}Time", "Chance", "Attempts");
+
+    for confidence in difficulty::PERCENTILES {
+        let attempts = difficulty::attempts_for_confidence(probability, confidence);
+        println!(
+            "{:<12}{:<28}{}",
+            format!("{}%", confidence * 100.0),
+            format_count(attempts),
+            format_seconds(attempts / attempts_per_second)
+        );
+    }
+}
+
+fn format_count(value: f64) -> String {
+    if value >= 1e18 {
+        return format!("{:.3e}", value);
+    }
+    HumanCount(value.ceil() as u64).to_string()
+}
+
+fn format_seconds(seconds: f64) -> String {
+    // Duration cannot represent the time scales of long patterns
+    const YEAR: f64 = 365.25 * 24.0 * 3600.0;
+    if seconds >= 1000.0 * YEAR {
+        return format!("{:.3e} years", seconds / YEAR);
+    }
+    if seconds < 1.0 {
+        return "less than a second".to_string();
+    }
+    HumanDuration(Duration::from_secs_f64(seconds)).to_string()
+}
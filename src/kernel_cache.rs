@@ -0,0 +1,90 @@
+use alloy_primitives::{hex, keccak256};
+use ocl::{
+    enums::{DeviceInfo, ProgramInfo, ProgramInfoResult},
+    Context, Device, Platform, Program,
+};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Build the OpenCL program for `src`, reusing the program binary cached on disk for the
+/// device and source, and caching the binary after compiling it otherwise.
+///
+/// Compiling the kernel takes seconds on some drivers. Cache entries are keyed by the
+/// platform, the device and its driver version as well as the hash of the generated
+/// source, so a driver update or a different target compiles afresh.
+pub fn build_program(platform: Platform, device: Device, context: &Context, src: &str) -> Program {
+    let path = cache_path(platform, device, src);
+
+    if let Some(binary) = path.as_ref().and_then(|path| fs::read(path).ok()) {
+        let binaries = [&binary[..]];
+        // a corrupt or incompatible binary is compiled again and replaced below
+        if let Ok(program) = Program::builder()
+            .devices(device)
+            .binaries(&binaries)
+            .build(context)
+        {
+            return program;
+        }
+    }
+
+    let program = Program::builder()
+        .devices(device)
+        .src(src)
+        .build(context)
+        .unwrap();
+
+    if let Some(path) = path {
+        if let Err(err) = store(&program, &path) {
+            eprintln!("Could not cache the OpenCL program binary: {}", err);
+        }
+    }
+
+    program
+}
+
+/// Directory of the cached program binaries, under `$XDG_CACHE_HOME` or `~/.cache`,
+/// falling back to the temporary directory.
+pub fn cache_dir() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(env::temp_dir)
+        .join("salty")
+        .join("kernels")
+}
+
+/// Cache file for `src` built for `device`, `None` if the device cannot be identified.
+fn cache_path(platform: Platform, device: Device, src: &str) -> Option<PathBuf> {
+    let key = [
+        platform.name().ok()?,
+        platform.version().ok()?,
+        device.vendor().ok()?,
+        device.name().ok()?,
+        device.info(DeviceInfo::DriverVersion).ok()?.to_string(),
+        src.to_string(),
+    ]
+    .join("\0");
+
+    Some(cache_dir().join(format!("{}.bin", hex::encode(keccak256(key)))))
+}
+
+/// Write the binary of `program` to `path`.
+fn store(program: &Program, path: &Path) -> io::Result<()> {
+    let binary = match program.info(ProgramInfo::Binaries) {
+        Ok(ProgramInfoResult::Binaries(binaries)) => binaries.into_iter().next(),
+        _ => None,
+    }
+    .filter(|binary| !binary.is_empty())
+    .ok_or_else(|| io::Error::other("the driver returned no program binary"))?;
+
+    fs::create_dir_all(path.parent().unwrap())?;
+
+    // write to a temporary file first, so a concurrent run never reads a partial binary
+    let partial = path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&partial, binary)?;
+    fs::rename(&partial, path)
+}
@@ -0,0 +1,248 @@
+/*
+   Copyright 2018 Lip Wee Yeo Amano
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+/******** Address search, shared by all Keccak-f[1600] kernels ********/
+
+// Follows one of the permutation kernels, which define
+//   keccakf(ulong *a):     Keccak-f[1600], of which only the address lanes (the
+//                          upper half of lane 1 and lanes 2 and 3) have to be correct
+//   keccakfFull(ulong *a): the complete Keccak-f[1600] permutation
+
+static inline bool matchesPattern(uchar const *d, __constant uchar const *pattern, __constant uchar const *mask) {
+#pragma unroll
+  for (uint i = 0; i < 20; ++i) {
+    if ((d[i] & mask[i]) != pattern[i])
+      return false;
+  }
+
+  return true;
+}
+
+#if defined(RANKING_ZERO_BYTES)
+// zero bytes in the upper bits, ties broken by zero nibbles (at most 40) in the lower 6
+static inline uint scoreAddress(uchar const *d) {
+  uint bytes = 0;
+  uint nibbles = 0;
+#pragma unroll
+  for (uint i = 0; i < 20; ++i) {
+    bytes += d[i] == 0;
+    nibbles += (d[i] >> 4) == 0;
+    nibbles += (d[i] & 0xf) == 0;
+  }
+
+  return (bytes << 6) | nibbles;
+}
+#elif defined(RANKING_REPEATED_NIBBLES)
+// number of leading nibbles equal to the first one
+static inline uint scoreAddress(uchar const *d) {
+  uchar const first = d[0] >> 4;
+  if ((d[0] & 0xf) != first)
+    return 1;
+
+  for (uint i = 1; i < 20; ++i) {
+    if ((d[i] >> 4) != first)
+      return 2 * i;
+    if ((d[i] & 0xf) != first)
+      return 2 * i + 1;
+  }
+
+  return 40;
+}
+#elif defined(RANKING_REPEATED_BYTES)
+// number of leading bytes equal to the first one
+static inline uint scoreAddress(uchar const *d) {
+  for (uint i = 1; i < 20; ++i) {
+    if (d[i] != d[0])
+      return i;
+  }
+
+  return 20;
+}
+#else
+#define scoreAddress(d) 0u
+#endif
+
+#if defined(CONTAINS_LEN)
+__constant uchar containsNibbles[CONTAINS_LEN] = {CONTAINS_NIBBLES};
+
+// whether the nibbles appear anywhere in the address, at any nibble offset
+static inline bool containsPattern(uchar const *d) {
+  for (uint pos = 0; pos + CONTAINS_LEN <= 40; ++pos) {
+    uint i = 0;
+    for (; i < CONTAINS_LEN; ++i) {
+      uint const n = pos + i;
+      uchar const nibble = (n & 1) ? (d[n >> 1] & 0xf) : (d[n >> 1] >> 4);
+      if (nibble != containsNibbles[i])
+        break;
+    }
+    if (i == CONTAINS_LEN)
+      return true;
+  }
+
+  return false;
+}
+#else
+#define containsPattern(d) true
+#endif
+
+// matchesExpression(d) is generated by the host from a pattern expression
+#if !defined(EXPRESSION)
+#define matchesExpression(d) true
+#endif
+
+// The message lanes are assembled and padded on the host, with the nonce bytes
+// zeroed. The host defines NONCE_LANES(s, n), which ORs the bytes of the 64-bit
+// nonce n into their lanes of s (see the salt template), and for Safe proxies
+// SALT_HASH_LANES(s, h), which places the 32-byte salt hash h into the CREATE2
+// message.
+//
+// Hashes the salt with nonce n, leaving the address in the upper half of lane 1
+// and lanes 2 and 3 of spongeBuffer.
+static inline void hashAddress(__constant ulong const *d_lanes, ulong const n,
+                               ulong *spongeBuffer) {
+  // the first block, holding the mined part of the salt
+#pragma unroll
+  for (int i = 0; i < 25; ++i)
+    spongeBuffer[i] = d_lanes[i];
+
+  NONCE_LANES(spongeBuffer, n);
+
+#if defined(ZKSYNC)
+  // zkSync Era hashes keccak256("zksyncCreate2") ++ sender ++ salt ++ bytecodeHash
+  // ++ keccak256(constructorInput), a 160-byte message spanning two blocks
+  keccakfFull(spongeBuffer);
+
+  // absorb the second block, the rest of the constructor input hash and padding
+#pragma unroll
+  for (int i = 0; i < 17; ++i)
+    spongeBuffer[i] ^= d_lanes[25 + i];
+#elif defined(SAFE_PROXY)
+  // the mined salt is the saltNonce, the proxy factory uses
+  // keccak256(keccak256(initializer) ++ saltNonce) as the CREATE2 salt
+  keccakfFull(spongeBuffer);
+
+  ulong saltHash[4];
+#pragma unroll
+  for (int i = 0; i < 4; ++i)
+    saltHash[i] = spongeBuffer[i];
+
+  // the CREATE2 message, with the salt zeroed
+#pragma unroll
+  for (int i = 0; i < 25; ++i)
+    spongeBuffer[i] = d_lanes[25 + i];
+
+  SALT_HASH_LANES(spongeBuffer, saltHash);
+#endif
+
+  // Apply keccakf
+  keccakf(spongeBuffer);
+}
+
+// Whether the address in spongeBuffer meets the constraints
+static inline bool isHit(ulong *spongeBuffer, __constant uchar const *pattern,
+                         __constant uchar const *mask, uint const threshold) {
+  uchar const *digest = (uchar const *)spongeBuffer + 12;
+
+#if defined(PATTERN_WORDS)
+  // the pattern is compiled in, compared a 64-bit lane at a time with early exit
+  bool const matched = PATTERN_WORDS(spongeBuffer);
+#else
+  bool const matched = matchesPattern(digest, pattern, mask);
+#endif
+
+  return matched && containsPattern(digest) && matchesExpression(digest) &&
+         scoreAddress(digest) >= threshold;
+}
+
+__kernel void hashMessage(__constant ulong const *d_lanes,
+                          ulong const firstNonce,
+                          __constant uchar const *pattern,
+                          __constant uchar const *mask,
+                          uint const threshold,
+                          __global volatile ulong *restrict solutions) {
+
+  ulong spongeBuffer[25];
+
+  // counting from the first nonce of the launch
+  ulong const nonce = firstNonce + get_global_id(0);
+
+  hashAddress(d_lanes, nonce, spongeBuffer);
+
+  if (isHit(spongeBuffer, pattern, mask, threshold)) {
+    // To be honest, if we are using OpenCL,
+    // we just need to write one solution for all practical purposes,
+    // since the chance of multiple solutions appearing
+    // in a single workset is extremely low. Any nonce including 0 can be a
+    // hit, so a hit is flagged separately; racing writers all write hits.
+    solutions[0] = nonce;
+    solutions[1] = 1;
+  }
+}
+
+// Self-test of the search as compiled for the run, checked by the host against
+// its own hashing: the address of the salt with nonce firstNonce + i as lanes 1
+// to 3, and whether it is a hit, in results[4 * i] to results[4 * i + 3]
+__kernel void hashSelfTest(__constant ulong const *d_lanes,
+                           ulong const firstNonce,
+                           __constant uchar const *pattern,
+                           __constant uchar const *mask,
+                           uint const threshold,
+                           __global ulong *results) {
+  size_t const i = get_global_id(0);
+  ulong spongeBuffer[25];
+
+  hashAddress(d_lanes, firstNonce + i, spongeBuffer);
+
+  results[4 * i] = spongeBuffer[1];
+  results[4 * i + 1] = spongeBuffer[2];
+  results[4 * i + 2] = spongeBuffer[3];
+  results[4 * i + 3] = isHit(spongeBuffer, pattern, mask, threshold);
+}
+
+// Self-test of the constraints as compiled for the run: whether the address given
+// as lanes 1 to 3 in addresses[3 * i] to addresses[3 * i + 2] is a hit
+__kernel void matchSelfTest(__global ulong const *addresses,
+                            __constant uchar const *pattern,
+                            __constant uchar const *mask,
+                            uint const threshold,
+                            __global ulong *results) {
+  size_t const i = get_global_id(0);
+  ulong spongeBuffer[25] = {0};
+
+  spongeBuffer[1] = addresses[3 * i];
+  spongeBuffer[2] = addresses[3 * i + 1];
+  spongeBuffer[3] = addresses[3 * i + 2];
+
+  results[i] = isHit(spongeBuffer, pattern, mask, threshold);
+}
+
+// Known-answer test of the permutations, the host checks the states against its
+// own Keccak-f[1600] before mining
+__kernel void keccakSelfTest(__global ulong *states) {
+  ulong a[25];
+
+  for (int i = 0; i < 25; ++i)
+    a[i] = states[i];
+  keccakf(a);
+  for (int i = 0; i < 25; ++i)
+    states[i] = a[i];
+
+  for (int i = 0; i < 25; ++i)
+    a[i] = states[25 + i];
+  keccakfFull(a);
+  for (int i = 0; i < 25; ++i)
+    states[25 + i] = a[i];
+}
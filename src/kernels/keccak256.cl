@@ -30,6 +30,9 @@
 
 /******** Keccak-f[1600] (for finding efficient Ethereum addresses) ********/
 
+// The fully unrolled permutation, the default kernel. keccakf only computes the
+// lanes holding the address in the last round, keccakfFull the whole state.
+
 #define OPENCL_PLATFORM_UNKNOWN 0
 #define OPENCL_PLATFORM_AMD 2
 
@@ -41,12 +44,6 @@
 #pragma OPENCL EXTENSION cl_amd_media_ops : enable
 #endif
 
-typedef union _nonce_t {
-  ulong uint64_t;
-  uint uint32_t[2];
-  uchar uint8_t[8];
-} nonce_t;
-
 #if PLATFORM == OPENCL_PLATFORM_AMD
 static inline ulong rol(const ulong x, const uint s) {
   uint2 output;
@@ -201,150 +198,33 @@ static inline void keccakf(ulong *a) {
 #undef o
 }
 
-static inline bool matchesPattern(uchar const *d, __constant uchar const *pattern, uint const pattern_len) {
-#pragma unroll
-  for (uint i = 0; i < pattern_len; ++i) {
-    if (d[i] != pattern[i])
-      return false;
-  }
-
-  return true;
-}
-
-__kernel void hashMessage(__constant uchar const *d_message,
-                          __constant uint const *d_nonce,
-                          __constant uchar const *pattern,
-                          uint const pattern_len,
-                          __global volatile ulong *restrict solutions) {
-
-  ulong spongeBuffer[25];
-
-#define sponge ((uchar *)spongeBuffer)
-#define digest (sponge + 12)
-
-  nonce_t nonce;
-
-  // write the control character
-  sponge[0] = 0xffu;
-
-  sponge[1] = S_1;
-  sponge[2] = S_2;
-  sponge[3] = S_3;
-  sponge[4] = S_4;
-  sponge[5] = S_5;
-  sponge[6] = S_6;
-  sponge[7] = S_7;
-  sponge[8] = S_8;
-  sponge[9] = S_9;
-  sponge[10] = S_10;
-  sponge[11] = S_11;
-  sponge[12] = S_12;
-  sponge[13] = S_13;
-  sponge[14] = S_14;
-  sponge[15] = S_15;
-  sponge[16] = S_16;
-  sponge[17] = S_17;
-  sponge[18] = S_18;
-  sponge[19] = S_19;
-  sponge[20] = S_20;
-  sponge[21] = S_21;
-  sponge[22] = S_22;
-  sponge[23] = S_23;
-  sponge[24] = S_24;
-  sponge[25] = S_25;
-  sponge[26] = S_26;
-  sponge[27] = S_27;
-  sponge[28] = S_28;
-  sponge[29] = S_29;
-  sponge[30] = S_30;
-  sponge[31] = S_31;
-  sponge[32] = S_32;
-  sponge[33] = S_33;
-  sponge[34] = S_34;
-  sponge[35] = S_35;
-  sponge[36] = S_36;
-  sponge[37] = S_37;
-  sponge[38] = S_38;
-  sponge[39] = S_39;
-  sponge[40] = S_40;
-
-  sponge[41] = d_message[0];
-  sponge[42] = d_message[1];
-  sponge[43] = d_message[2];
-  sponge[44] = d_message[3];
-
-  // populate the nonce
-  nonce.uint32_t[0] = get_global_id(0);
-  nonce.uint32_t[1] = d_nonce[0];
-
-  // populate the body of the message with the nonce
-  sponge[45] = nonce.uint8_t[0];
-  sponge[46] = nonce.uint8_t[1];
-  sponge[47] = nonce.uint8_t[2];
-  sponge[48] = nonce.uint8_t[3];
-  sponge[49] = nonce.uint8_t[4];
-  sponge[50] = nonce.uint8_t[5];
-  sponge[51] = nonce.uint8_t[6];
-  sponge[52] = nonce.uint8_t[7];
-
-  sponge[53] = S_53;
-  sponge[54] = S_54;
-  sponge[55] = S_55;
-  sponge[56] = S_56;
-  sponge[57] = S_57;
-  sponge[58] = S_58;
-  sponge[59] = S_59;
-  sponge[60] = S_60;
-  sponge[61] = S_61;
-  sponge[62] = S_62;
-  sponge[63] = S_63;
-  sponge[64] = S_64;
-  sponge[65] = S_65;
-  sponge[66] = S_66;
-  sponge[67] = S_67;
-  sponge[68] = S_68;
-  sponge[69] = S_69;
-  sponge[70] = S_70;
-  sponge[71] = S_71;
-  sponge[72] = S_72;
-  sponge[73] = S_73;
-  sponge[74] = S_74;
-  sponge[75] = S_75;
-  sponge[76] = S_76;
-  sponge[77] = S_77;
-  sponge[78] = S_78;
-  sponge[79] = S_79;
-  sponge[80] = S_80;
-  sponge[81] = S_81;
-  sponge[82] = S_82;
-  sponge[83] = S_83;
-  sponge[84] = S_84;
-
-  // begin padding based on message length
-  sponge[85] = 0x01u;
-
-  // fill padding
-#pragma unroll
-  for (int i = 86; i < 135; ++i)
-    sponge[i] = 0;
-
-  // end padding
-  sponge[135] = 0x80u;
-
-  // fill remaining sponge state with zeroes
-#pragma unroll
-  for (int i = 136; i < 200; ++i)
-    sponge[i] = 0;
-
-  // Apply keccakf
-  keccakf(spongeBuffer);
+// Full Keccak-f[1600] permutation, used where the whole digest is needed
+static inline void keccakfFull(ulong *a) {
+  ulong b[5];
+  ulong t;
 
-  // determine if the address meets the constraints
-  if (matchesPattern(digest, pattern, pattern_len)) {
-    // To be honest, if we are using OpenCL,
-    // we just need to write one solution for all practical purposes,
-    // since the chance of multiple solutions appearing
-    // in a single workset is extremely low.
-    solutions[0] = nonce.uint64_t;
-  }
+  iteration(0x0000000000000001);
+  iteration(0x0000000000008082);
+  iteration(0x800000000000808a);
+  iteration(0x8000000080008000);
+  iteration(0x000000000000808b);
+  iteration(0x0000000080000001);
+  iteration(0x8000000080008081);
+  iteration(0x8000000000008009);
+  iteration(0x000000000000008a);
+  iteration(0x0000000000000088);
+  iteration(0x0000000080008009);
+  iteration(0x000000008000000a);
+  iteration(0x000000008000808b);
+  iteration(0x800000000000008b);
+  iteration(0x8000000000008089);
+  iteration(0x8000000000008003);
+  iteration(0x8000000000008002);
+  iteration(0x8000000000000080);
+  iteration(0x000000000000800a);
+  iteration(0x800000008000000a);
+  iteration(0x8000000080008081);
+  iteration(0x8000000000008080);
+  iteration(0x0000000080000001);
+  iteration(0x8000000080008008);
 }
@@ -28,7 +28,8 @@
 
 /******** The Keccak-f[1600] permutation ********/
 
-#define LEADING_ZEROES 4
+// A compact permutation looping over the 24 rounds, selected with `--kernel looped`.
+// It computes the whole state, so keccakf and keccakfFull are the same.
 
 #define OPENCL_PLATFORM_UNKNOWN 0
 #define OPENCL_PLATFORM_AMD 2
@@ -41,19 +42,6 @@
 #pragma OPENCL EXTENSION cl_amd_media_ops : enable
 #endif
 
-#define ADDRESS_LENGTH 20u
-#define UINT64_LENGTH 8u
-#define UINT256_LENGTH 32u
-#define MESSAGE_LENGTH 84u
-#define SPONGE_LENGTH 200u
-#define NONCE_POSITION UINT256_LENGTH + ADDRESS_LENGTH + ADDRESS_LENGTH
-
-typedef union _nonce_t {
-  ulong uint64_t;
-  uint uint32_t[2];
-  uchar uint8_t[8];
-} nonce_t;
-
 static inline ulong rol(const ulong x, const uint s) {
 #if PLATFORM == OPENCL_PLATFORM_AMD
 
@@ -71,9 +59,6 @@ static inline ulong rol(const ulong x, const uint s) {
 #endif
 }
 
-#define delim 0x01
-#define rate SPONGE_LENGTH - (256 / 4)
-
 /*** Constants. ***/
 __constant static ulong const Keccak_f1600_RC[24] = {
     0x0000000000000001, 0x0000000000008082, 0x800000000000808a,
@@ -93,19 +78,8 @@ __constant static const uchar pi[24] = {10, 7,  11, 17, 18, 3,  5,  16,
                                         8,  21, 24, 4,  15, 23, 19, 13,
                                         12, 2,  20, 14, 22, 9,  6,  1};
 
-static inline void setout(const uchar *src, uchar *dst, size_t len) {
-  for (size_t i = 0; i < len; i += 1)
-    dst[i] = src[i];
-}
-
-static inline void xorin(uchar *dst, const uchar *src, size_t len) {
-  for (size_t i = 0; i < len; i += 1)
-    dst[i] ^= src[i];
-}
-
-/*** This is the unrolled version of the original macro ***/
-static inline void keccakf(void *state) {
-  ulong *a = (ulong *)state;
+/*** A loop over the rounds, with the steps of each round unrolled ***/
+static inline void keccakf(ulong *a) {
   ulong b[5] = {0, 0, 0, 0, 0};
   ulong t;
 
@@ -306,105 +280,4 @@ static inline void keccakf(void *state) {
   }
 }
 
-static inline void keccak256(uchar *digest, uchar const *message) {
-  uchar sponge[SPONGE_LENGTH];
-  uint messageLength = MESSAGE_LENGTH;
-  uint digestLength = UINT256_LENGTH;
-
-  for (uchar i = 0; i < SPONGE_LENGTH; ++i)
-    sponge[i] = 0;
-
-  // Absorb input
-  while (messageLength >= (SPONGE_LENGTH - 64u)) {
-    xorin(sponge, message, SPONGE_LENGTH - 64u);
-    keccakf(sponge);
-    message += (SPONGE_LENGTH - 64u);
-    messageLength -= (SPONGE_LENGTH - 64u);
-  }
-
-  // Xor in the DS and pad frame
-  sponge[messageLength] ^= 0x01u;
-  sponge[SPONGE_LENGTH - 65u] ^= 0x80u;
-
-  // Xor in the last block
-  xorin(sponge, message, messageLength);
-
-  // Apply keccakf
-  keccakf(sponge);
-
-  // Squeeze output
-  while (digestLength >= (SPONGE_LENGTH - 64u)) {
-    setout(sponge, digest, SPONGE_LENGTH - 64u);
-    keccakf(sponge);
-    digest += (SPONGE_LENGTH - 64u);
-    digestLength -= (SPONGE_LENGTH - 64u);
-  };
-
-  setout(sponge, digest, digestLength);
-}
-
-static inline bool hasLeading(uchar const *d) {
-  // #pragma unroll
-  for (uint i = 0; i < LEADING_ZEROES; ++i) {
-    if (d[i] != 0)
-      return false;
-  }
-
-  return true;
-}
-
-__kernel void hashMessage(
-    //   __constant uchar const *d_target,
-    __constant uchar const *d_factory,  // Factory Address
-    __constant uchar const *d_caller,   // Caller Address
-    __constant uchar const *d_codehash, // Codehash
-    __constant uchar const *d_salt,     // First 4 bytes of the salt
-    //   __constant uchar const *d_target,
-    __constant uint const *d_nonce, // Nonce for the next 4 bytes of the salt
-    //   uint const maxSolutionCount,
-    __global volatile ulong *restrict solutions
-    //   __global volatile uint *solutionCount
-) {
-  uchar digest[UINT256_LENGTH];
-  uchar message[MESSAGE_LENGTH];
-
-  // (20) First 20 bytes
-  for (uchar i = 0; i < ADDRESS_LENGTH; ++i)
-    message[i] = d_factory[i];
-
-  // (40) Next 20 bytes
-  for (uchar i = 0; i < ADDRESS_LENGTH; ++i)
-    message[ADDRESS_LENGTH + i] = d_caller[i];
-
-  // (44) Next 4 bytes
-  for (uchar i = 0; i < 4; ++i)
-    message[ADDRESS_LENGTH * 2 + i] = d_salt[i];
-
-  nonce_t nonce;
-  //   nonce.uint64_t = d_nonce + get_global_id(0);
-  nonce.uint32_t[0] = get_global_id(0);
-  nonce.uint32_t[1] = d_nonce[0];
-
-  // (52) Next 8 bytes
-  for (uchar i = 0; i < UINT64_LENGTH; ++i)
-    message[ADDRESS_LENGTH * 2 + 4 + i] = nonce.uint8_t[i];
-
-  // (84) Next 32 bytes
-  for (uchar i = 0; i < UINT256_LENGTH; ++i)
-    message[ADDRESS_LENGTH * 2 + 4 + UINT64_LENGTH + i] = d_codehash[i];
-
-  keccak256(digest, message);
-
-  if (hasLeading(digest)) {
-    for (uchar i = 0; i < UINT256_LENGTH; ++i)
-      printf("%02x", digest[i]);
-    printf("\n");
-
-    for (uchar i = 0; i < MESSAGE_LENGTH; ++i)
-      printf("%02x", message[i]);
-    printf("\n");
-
-    solutions[0] = nonce.uint64_t;
-    // ++solutionCount[0];
-  }
-}
\ No newline at end of file
+static inline void keccakfFull(ulong *a) { keccakf(a); }
@@ -0,0 +1,108 @@
+use alloy_primitives::hex;
+use k256::SecretKey;
+use rand::rngs::OsRng;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::Display;
+use salty::core::eoa::{address_of, search_keys, FoundKey, KeyTarget};
+
+/// Number of consecutive keys each thread tries before picking a fresh random start.
+const KEY_BATCH: u32 = 0x10000;
+
+/// Search for a private key whose `target` address starts with `pattern` using
+/// `threads` CPU threads, then store it encrypted with `password` as a keystore JSON
+/// file in `keystore_dir`.
+///
+/// Every batch starts from a fresh key drawn from the operating system's CSPRNG and
+/// steps through consecutive keys from there. The private key is never printed; only
+/// the address and the path of the keystore file are.
+pub fn start_key_miner(
+    pattern: Vec<u8>,
+    target: KeyTarget,
+    threads: usize,
+    keystore_dir: &Path,
+    password: &str,
+    display: Display,
+) {
+    println!("Preparing CPU Key Miner ({} threads)...", threads);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let (sender, receiver) = mpsc::channel::<FoundKey>();
+
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let pattern = pattern.clone();
+            let stop = stop.clone();
+            let attempts = attempts.clone();
+            let sender = sender.clone();
+
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let start = SecretKey::random(&mut OsRng);
+                    let (found, tried) = search_keys(&pattern, target, &start, KEY_BATCH);
+                    attempts.fetch_add(tried as u64, Ordering::Relaxed);
+
+                    if let Some(found) = found {
+                        stop.store(true, Ordering::Relaxed);
+                        let _ = sender.send(found);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    display.start();
+
+    let found = loop {
+        match receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(found) => break found,
+            Err(_) => {
+                let work_rate = attempts.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+                display.update(work_rate, &format!("0x{}", hex::encode(&pattern)), &vec![]);
+            }
+        }
+    };
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let secret = SecretKey::from_slice(&found.secret).unwrap();
+    assert_eq!(address_of(&secret), found.address);
+
+    std::fs::create_dir_all(keystore_dir).unwrap();
+    let name = format!("{}.json", found.address);
+    eth_keystore::encrypt_key(
+        keystore_dir,
+        &mut OsRng,
+        found.secret,
+        password,
+        Some(&name),
+    )
+    .unwrap();
+
+    // make sure the key can be recovered before reporting success
+    let decrypted = eth_keystore::decrypt_key(keystore_dir.join(&name), password).unwrap();
+    assert_eq!(decrypted, found.secret);
+
+    match target {
+        KeyTarget::Account => {
+            println!("{} (Pattern: {})", found.address, hex::encode(&pattern));
+        }
+        KeyTarget::Create(nonce) => {
+            println!(
+                "{} => {} at nonce {} (Pattern: {})",
+                found.address,
+                found.target,
+                nonce,
+                hex::encode(&pattern)
+            );
+        }
+    }
+    println!("Keystore written to {}", keystore_dir.join(&name).display());
+}
@@ -1,19 +1,28 @@
+use alloy_primitives::{hex, Address};
 use clap::{Parser, Subcommand};
+use console::Term;
 use figment::{
     providers::{Format, Serialized, Toml},
     Figment,
 };
+use salty::core::{
+    create::scan_nonces, eoa::KeyTarget, kernel::KeccakKernel, parse_config, parse_pattern,
+    self_test, MinerConfig, RawConfig,
+};
 use serde::{Deserialize, Serialize};
-use std::process;
+use std::{env, path::PathBuf, process, thread, time::Duration};
 
-mod core;
+mod cpu_miner;
 mod display;
 mod gpgpu;
+mod kernel_cache;
+mod key_miner;
 mod miner;
 
-use crate::core::{parse_config, RawConfig};
+pub use cpu_miner::start_cpu_miner;
 pub use display::Display;
-pub use miner::start_miner;
+pub use key_miner::start_key_miner;
+pub use miner::{start_miner, KernelOptions};
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
 struct MineArgs {
@@ -41,53 +50,334 @@ struct MineArgs {
     #[arg(short, long)]
     #[serde(skip_serializing_if = "::std::option::Option::is_none")]
     pattern: Option<String>,
+
+    /// How the pattern is matched: 'prefix' (default), 'contains' for anywhere or 'expression' (e.g., '^(dead|beef)[0-9]{4}')
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    match_mode: Option<String>,
+
+    /// Uniswap v4 hook callbacks encoded in the low address bits (e.g., 'beforeSwap,afterSwap' or 'none')
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    hooks: Option<String>,
+
+    /// Rank addresses and only report improvements: 'zero-bytes', 'repeated-nibbles' or 'repeated-bytes'
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    ranking: Option<String>,
+
+    /// Minimum score of a reported address in a ranking mode (e.g., number of zero bytes)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    min_score: Option<u32>,
+
+    /// Salt layout (e.g., 'caller,random:4,nonce', '0xcafe,zero:18,random:4,nonce' or 'zero-caller')
+    #[arg(short, long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    salt: Option<String>,
+
+    /// Salt derivation: 'create2' (default), 'clone' for EIP-1167 clones or 'safe' for Safe proxies
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    derivation: Option<String>,
+
+    /// Safe singleton (mastercopy) address, for the 'safe' derivation
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    singleton: Option<String>,
+
+    /// Safe setup calldata passed as the initializer, for the 'safe' derivation
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    initializer: Option<String>,
+
+    /// Implementation address of the minimal proxy, for the 'clone' derivation
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    implementation: Option<String>,
+
+    /// Immutable args appended to the clone (cloneDeterministicWithImmutableArgs), for the 'clone' derivation
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    clone_args: Option<String>,
+
+    /// Chain address derivation: 'ethereum' (default) or 'zksync' for zkSync Era CREATE2
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    chain: Option<String>,
+
+    /// ABI-encoded constructor arguments, hashed into the address on zkSync Era
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    constructor_input: Option<String>,
+
+    /// Proxy creation code returned by the factory's proxyCreationCode(), for the 'safe' derivation
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    proxy_code: Option<String>,
+
+    /// Seed of the searched keyspace, to repeat a previous run (random if omitted)
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    seed: Option<u64>,
+
+    /// Keccak kernel: 'unrolled' (default), 'looped', or the path of a .cl file defining
+    /// keccakf and keccakfFull
+    #[arg(long)]
+    #[serde(skip_serializing_if = "::std::option::Option::is_none")]
+    kernel: Option<String>,
+
+    /// Compile the OpenCL kernel from source instead of reusing a cached program binary
+    #[arg(long)]
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    no_kernel_cache: bool,
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+struct EstimateArgs {
+    #[command(flatten)]
+    target: MineArgs,
+
+    /// Speed in million attempts per second (benchmarks the OpenCL device if omitted)
+    #[arg(short, long)]
+    rate: Option<f64>,
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+struct BenchArgs {
+    #[command(flatten)]
+    target: MineArgs,
+
+    /// Seconds to run each kernel variant for
+    #[arg(short, long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+    duration: u64,
+
+    /// Compare assembling every message byte by byte with the precomputed lanes on one CPU
+    /// thread instead of benchmarking the OpenCL kernels
+    #[arg(long)]
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    lanes: bool,
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+struct MineCpuArgs {
+    #[command(flatten)]
+    target: MineArgs,
+
+    /// Number of CPU threads (defaults to all available)
+    #[arg(short, long)]
+    threads: Option<u32>,
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+struct MineKeyArgs {
+    /// Hex pattern to match at start of the account address (e.g., '01010101')
+    #[arg(short, long)]
+    pattern: String,
+
+    /// Number of CPU threads (defaults to all available)
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// Directory the encrypted keystore file is written to
+    #[arg(short, long, default_value = "keystore")]
+    keystore: PathBuf,
+
+    /// Match the address of the contract deployed with CREATE at this nonce instead
+    #[arg(long)]
+    create_nonce: Option<u64>,
+}
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
+struct ScanNoncesArgs {
+    /// Deployer Address
+    #[arg(short, long)]
+    deployer: String,
+
+    /// Hex pattern to match at start of the contract address (e.g., '01010101')
+    #[arg(short, long)]
+    pattern: String,
+
+    /// First nonce to check
+    #[arg(long, default_value_t = 0)]
+    from: u64,
+
+    /// Nonce to stop at (exclusive)
+    #[arg(long, default_value_t = 1_000_000)]
+    to: u64,
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize)]
 enum Commands {
     /// Start Create2 Salt Miner
     Mine(MineArgs),
+    /// Start Create2 Salt Miner on the CPU threads instead of the OpenCL device
+    MineCpu(MineCpuArgs),
+    /// Mine a private key for a vanity account address, stored as an encrypted keystore
+    MineKey(MineKeyArgs),
+    /// Find the nonces at which a deployer creates contracts at matching addresses
+    ScanNonces(ScanNoncesArgs),
+    /// Estimate the difficulty and time needed to find a match
+    Estimate(EstimateArgs),
+    /// Benchmark the OpenCL kernel with a generic and a pattern specialized build
+    Bench(BenchArgs),
     /// List available OpenCL Platforms (& Devices), including default
     List {},
 }
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
 #[command(name = "Salty", author, version, about, long_about = None)]
-struct CLI {
+struct Cli {
     #[command(subcommand)]
     mode: Commands,
 }
 
+/// Prompt for the keystore password on the terminal, asking twice to catch typos.
+fn read_password() -> String {
+    let term = Term::stderr();
+    loop {
+        term.write_str("Keystore password: ").unwrap();
+        let password = term.read_secure_line().unwrap();
+        if password.is_empty() {
+            term.write_line("The password must not be empty, try again.")
+                .unwrap();
+            continue;
+        }
+        term.write_str("Repeat password: ").unwrap();
+        if password == term.read_secure_line().unwrap() {
+            return password;
+        }
+        term.write_line("Passwords do not match, try again.")
+            .unwrap();
+    }
+}
+
+/// Merge the CLI arguments over the values from `salty.toml`.
+fn load_args(args: &MineArgs) -> MineArgs {
+    Figment::new()
+        .merge(Toml::file("salty.toml"))
+        .merge(Serialized::defaults(args))
+        .extract()
+        .unwrap()
+}
+
+/// Parse the mining target for a benchmark along with its OpenCL build options, exiting
+/// on invalid parameters.
+fn benchmark_config(target: &MineArgs) -> (MinerConfig, KernelOptions) {
+    let mut unwrapped = load_args(target);
+    let options = kernel_options(&unwrapped);
+
+    // the deployment parameters do not affect the difficulty, only the benchmark kernel
+    unwrapped
+        .caller
+        .get_or_insert(format!("0x{}", "00".repeat(20)));
+    unwrapped
+        .codehash
+        .get_or_insert(format!("0x{}", "00".repeat(32)));
+
+    match parse_config(raw_config(unwrapped)) {
+        Ok(cfg) => (cfg, options),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// OpenCL build options of the mining target, exiting on an unknown kernel.
+fn kernel_options(args: &MineArgs) -> KernelOptions {
+    let kernel = match args.kernel.as_deref().map(KeccakKernel::load) {
+        None => KeccakKernel::default(),
+        Some(Ok(kernel)) => kernel,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    KernelOptions {
+        cache: !args.no_kernel_cache,
+        kernel,
+    }
+}
+
+fn raw_config(args: MineArgs) -> RawConfig {
+    RawConfig {
+        factory: args
+            .factory
+            .unwrap_or("0x0000000000FFe8B47B3e2130213B802212439497".to_string()),
+        caller: args.caller,
+        codehash: args.codehash,
+        worksize: args.worksize.unwrap_or(0x4400000),
+        // hook flags and rankings are valid targets alone, otherwise default to a leading zero byte
+        pattern: args.pattern.unwrap_or_else(|| {
+            if args.hooks.is_some() || args.ranking.is_some() {
+                String::new()
+            } else {
+                "00".to_string()
+            }
+        }),
+        match_mode: args.match_mode,
+        hooks: args.hooks,
+        ranking: args.ranking,
+        min_score: args.min_score,
+        salt: args.salt,
+        derivation: args.derivation,
+        singleton: args.singleton,
+        initializer: args.initializer,
+        proxy_code: args.proxy_code,
+        implementation: args.implementation,
+        clone_args: args.clone_args,
+        chain: args.chain,
+        constructor_input: args.constructor_input,
+    }
+}
+
 #[cfg(feature = "cli")]
 fn main() {
-    let cli = CLI::parse();
+    let cli = Cli::parse();
 
     match &cli.mode {
         Commands::Mine(args) => {
-            let unwrapped: MineArgs = Figment::new()
-                .merge(Toml::file("salty.toml"))
-                .merge(Serialized::defaults(args))
-                .extract()
-                .unwrap();
+            let unwrapped = load_args(args);
 
             println!("{:#?}", unwrapped);
 
-            if unwrapped.caller.is_none() || unwrapped.codehash.is_none() {
-                eprintln!("Insufficient arguments provided. Please see --help for usage.");
+            let seed = unwrapped.seed.unwrap_or_else(rand::random);
+            let options = kernel_options(&unwrapped);
+
+            let app_config = match parse_config(raw_config(unwrapped)) {
+                Ok(cfg) => cfg,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            };
+
+            if let Err(err) = miner::check_kernel_support(&app_config) {
+                eprintln!("{}", err);
                 process::exit(1);
             }
 
-            let raw = RawConfig {
-                factory: unwrapped
-                    .factory
-                    .unwrap_or("0x0000000000FFe8B47B3e2130213B802212439497".to_string()),
-                caller: unwrapped.caller.unwrap_or("0x00".to_string()),
-                codehash: unwrapped.codehash.unwrap_or("0x00".to_string()),
-                worksize: unwrapped.worksize.unwrap_or(0x4400000 as u32),
-                pattern: unwrapped.pattern.unwrap_or("00".to_string()),
-            };
+            // the host hashing verifies every hit of the device, which start_miner tests
+            // once the program of the run is built
+            if let Err(err) = self_test::check_cpu() {
+                eprintln!("Self-test failed, not mining: {}.", err);
+                process::exit(1);
+            }
+
+            let display = Display::new();
+
+            start_miner(app_config, display, seed, &options);
+        }
+        Commands::MineCpu(args) => {
+            let unwrapped = load_args(&args.target);
+
+            println!("{:#?}", unwrapped);
 
-            let app_config = match parse_config(raw) {
+            let seed = unwrapped.seed.unwrap_or_else(rand::random);
+
+            let app_config = match parse_config(raw_config(unwrapped)) {
                 Ok(cfg) => cfg,
                 Err(err) => {
                     eprintln!("{}", err);
@@ -95,9 +385,149 @@ fn main() {
                 }
             };
 
+            // a broken CPU search would silently miss hits or report wrong salts
+            if let Err(err) = self_test::check_cpu() {
+                eprintln!("Self-test failed, not mining: {}.", err);
+                process::exit(1);
+            }
+
+            let threads = args.threads.unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |threads| threads.get() as u32)
+            });
+            if threads == 0 {
+                eprintln!("Thread count must not be zero.");
+                process::exit(1);
+            }
+
+            let display = Display::new();
+
+            start_cpu_miner(app_config, display, seed, threads);
+        }
+        Commands::MineKey(args) => {
+            let pattern = match parse_pattern(&args.pattern) {
+                Ok(pattern) => pattern,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            };
+
+            let threads = args.threads.unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |threads| threads.get())
+            });
+
+            // ask for the password upfront so the run does not block once a key is found
+            let password = match env::var("SALTY_KEYSTORE_PASSWORD") {
+                Ok(password) => password,
+                Err(_) => read_password(),
+            };
+            // an empty password leaves the key as good as unencrypted
+            if password.is_empty() {
+                eprintln!("SALTY_KEYSTORE_PASSWORD must not be empty.");
+                process::exit(1);
+            }
+
+            let target = args
+                .create_nonce
+                .map_or(KeyTarget::Account, KeyTarget::Create);
+
             let display = Display::new();
 
-            start_miner(app_config, display);
+            start_key_miner(pattern, target, threads, &args.keystore, &password, display);
+        }
+        Commands::ScanNonces(args) => {
+            let (deployer, pattern) = match (
+                args.deployer.parse::<Address>(),
+                parse_pattern(&args.pattern),
+            ) {
+                (Ok(deployer), Ok(pattern)) => (deployer, pattern),
+                (Err(_), _) => {
+                    eprintln!("Invalid deployer address: '{}'.", args.deployer);
+                    process::exit(1);
+                }
+                (_, Err(err)) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            };
+
+            let found = scan_nonces(&deployer, &pattern, args.from..args.to);
+            for (nonce, address) in &found {
+                println!(
+                    "{} => {} (Pattern: {})",
+                    nonce,
+                    address,
+                    hex::encode(&pattern)
+                );
+            }
+            println!(
+                "Found {} matching nonces between {} and {}.",
+                found.len(),
+                args.from,
+                args.to
+            );
+        }
+        Commands::Estimate(args) => {
+            let (app_config, options) = benchmark_config(&args.target);
+
+            let (rate, benchmarked) = match args.rate {
+                Some(rate) if rate > 0.0 => (rate, false),
+                Some(_) => {
+                    eprintln!("Rate must be a positive number of million attempts per second.");
+                    process::exit(1);
+                }
+                None => {
+                    if let Err(err) = miner::check_kernel_support(&app_config) {
+                        eprintln!("{} Pass --rate to estimate without a benchmark.", err);
+                        process::exit(1);
+                    }
+                    println!("Benchmarking OpenCL device...");
+                    let rate =
+                        miner::benchmark(&app_config, Duration::from_secs(3), true, &options);
+                    (rate, true)
+                }
+            };
+
+            display::print_estimate(&app_config, rate, benchmarked);
+        }
+        Commands::Bench(args) => {
+            let (app_config, options) = benchmark_config(&args.target);
+            let duration = Duration::from_secs(args.duration);
+
+            if args.lanes {
+                println!("Benchmarking one CPU thread...");
+                let (bytewise, lanes) = cpu_miner::benchmark_lanes(&app_config, duration);
+
+                println!(
+                    "Byte-wise messages: {:.2} million attempts per second",
+                    bytewise
+                );
+                println!(
+                    "Precomputed lanes:  {:.2} million attempts per second ({:.2}x)",
+                    lanes,
+                    lanes / bytewise
+                );
+                return;
+            }
+
+            if let Err(err) = miner::check_kernel_support(&app_config) {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+
+            println!("Benchmarking OpenCL device...");
+            let generic = miner::benchmark(&app_config, duration, false, &options);
+            let specialized = miner::benchmark(&app_config, duration, true, &options);
+
+            println!(
+                "Generic kernel:     {:.2} million attempts per second",
+                generic
+            );
+            println!(
+                "Specialized kernel: {:.2} million attempts per second ({:.2}x)",
+                specialized,
+                specialized / generic
+            );
         }
         Commands::List {} => {
             gpgpu::list_devices();
@@ -1,262 +1,676 @@
-use alloy_primitives::{hex, Address, FixedBytes, Keccak256};
-use ocl::{Buffer, Context, Device, MemFlags, Platform, ProQue, Program, Queue};
-use rand::Rng;
+use alloy_primitives::{hex, Address};
+use ocl::{Buffer, Context, Device, Event, Kernel, MemFlags, Platform, ProQue, Program, Queue};
+use std::collections::VecDeque;
 use std::fmt::Write;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use crate::{core::MinerConfig, Display};
-
-static KERNEL_SRC: &str = include_str!("./kernels/keccak256.cl");
-
-const CONTROL_CHARACTER: u8 = 0xff;
+use std::process;
+use std::time::{Duration, Instant};
+
+use crate::{kernel_cache, Display};
+use salty::core::{
+    compute_address,
+    keccak::{keccak_f, padded_block},
+    kernel::{kernel_src, KeccakKernel},
+    keyspace::{Keyspace, KeyspaceIndex, SaltPosition},
+    lanes::MessageLanes,
+    pattern::find_nibbles,
+    self_test::{hit_candidates, is_hit},
+    MinerConfig,
+};
 
 /// Given a `config` object with a factory address, a caller address, a keccak-256 hash
 /// of the contract initialization code, search for salts using OpenCL that will enable
 /// the factory contract to deploy a contract to a gas-efficient address via CREATE2.
 ///
-/// The 32-byte salt is constructed as follows:
+/// The 32-byte salt follows the configured salt template, by default
+/// `caller,random:4,nonce`:
 ///   - the 20-byte calling address (to prevent frontrunning)
 ///   - a random 4-byte segment (to prevent collisions with other runs)
-///   - a 4-byte segment unique to each work group running in parallel
-///   - a 4-byte nonce segment (incrementally stepped through during the run)
+///   - an 8-byte nonce, stepped through by the kernel
 ///
-/// When a salt that will result in the creation of a gas-efficient contract
-/// address is found, it will be displayed on the screen along with the resultant address
-/// and the "score" (i.e. how many leading zero bytes) of the resultant address.
+/// The salts are enumerated by a [`Keyspace`] seeded with `seed`: every launch searches
+/// the next batch of `worksize` nonces, and once the nonces of the random segment are
+/// used up, the search moves on to the next random segment.
 ///
-/// This method only searches for results better than what is already found. For example,
-/// if a salt is found that results in an address with 3 leading zero bytes, the next salt
-/// will only be displayed if it results in an address with 4 leading zero bytes.
+/// Every address reported by the device is recomputed on the host and displayed along
+/// with its salt if it matches the pattern, the `contains` nibbles or the pattern
+/// expression (with the position of the match). In a ranking mode, its score is shown
+/// as well, and only addresses scoring higher than the best one so far are reported
+/// afterwards.
 ///
 /// This method is highly experimental and could certainly use further optimization.
 /// Contributions are welcome as always!
-pub fn start_miner(config: MinerConfig, display: Display) {
+pub fn start_miner(config: MinerConfig, display: Display, seed: u64, options: &KernelOptions) {
     println!("Preparing OpenCL Miner...",);
+    println!("Seed: {} (pass --seed {} to repeat this run)", seed, seed);
 
-    let worksize = config.worksize.clone();
-    let workfactor = (worksize as u128) / 1_000_000;
+    let worksize = config.worksize;
 
     let mut found_list: Vec<String> = vec![];
 
+    // in a ranking mode, raised past the best score found so far after every hit
+    let mut threshold = config.min_score;
+
+    let program_queue = build_program_queue(&config, true, options);
+
+    // the device is the only worker of the run, every launch searches one batch
+    let keyspace = Keyspace::new(&config, seed, 1, worksize);
+
+    // a broken kernel would silently miss hits or report wrong salts
+    if let Err(err) = self_test(&program_queue, &config, &keyspace.random(0)) {
+        eprintln!("Self-test failed, not mining: {}.", err);
+        process::exit(1);
+    }
+
     display.start();
 
-    let platform = Platform::new(ocl::core::default_platform().unwrap());
-    let device = Device::by_idx_wrap(platform, 0 as usize).unwrap();
-    let context = Context::builder()
-        .platform(platform)
-        .devices(device)
+    let batch_position = |batch| {
+        keyspace.position(KeyspaceIndex {
+            worker: 0,
+            batch,
+            offset: 0,
+        })
+    };
+
+    // the next batch to launch
+    let mut batch: u64 = 0;
+
+    // the random segment of the salt only changes with the salt prefix, so the message
+    // lanes are assembled once per prefix and the nonce is filled in by the kernel
+    let mut prefix: u64 = 0;
+    let mut lanes_buffer = build_lanes_buffer(&program_queue, &config, &keyspace.random(prefix));
+
+    // create pattern & mask buffers
+    let pattern_buffer = Buffer::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.value)
         .build()
         .unwrap();
 
-    let program = Program::builder()
-        .devices(device)
-        .src(mk_kernel_src(&config))
-        .build(&context)
+    let mask_buffer = Buffer::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.mask)
+        .build()
         .unwrap();
 
-    let queue = Queue::new(&context, device, None).unwrap();
-    let program_queue = ProQue::new(context, queue, program, Some(worksize));
-
-    let mut rng = rand::thread_rng();
+    // the launches queued on the device, oldest first, and the free slots
+    let mut in_flight: VecDeque<LaunchSlot> = VecDeque::with_capacity(MAX_IN_FLIGHT);
+    let mut free: Vec<LaunchSlot> = (0..MAX_IN_FLIGHT)
+        .map(|_| LaunchSlot::new(&program_queue))
+        .collect();
+
+    // build the kernel once, the nonce, threshold and solutions are set per launch
+    let kernel = program_queue
+        .kernel_builder("hashMessage")
+        .arg_named("lanes", &lanes_buffer)
+        .arg_named("nonce", 0u64)
+        .arg(&pattern_buffer)
+        .arg(&mask_buffer)
+        .arg_named("threshold", threshold)
+        .arg_named("solutions", &free[0].solutions)
+        .build()
+        .unwrap();
 
     // set up variables for tracking performance
-    let mut cumulative_nonce: u64 = 0;
+    let mut launches: u64 = 0;
 
-    // the previous timestamp of printing to the terminal
-    let mut previous_time: u64 = 0;
-
-    // the last work duration in milliseconds
-    let mut work_duration_millis: u64 = 0;
+    // the last time the terminal was updated
+    let mut previous_update = Instant::now();
 
     loop {
-        // construct the 4-byte message to hash, leaving last 8 of salt empty
-        let salt = FixedBytes::<4>::random();
-        let salt_buffer = Buffer::builder()
-            .queue(program_queue.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(4)
-            .copy_host_slice(&salt[..])
-            .build()
-            .unwrap();
+        // top up the queue, so the device has the next batches at hand while the host
+        // waits for and verifies the oldest one
+        while in_flight.len() < MAX_IN_FLIGHT {
+            let Some(position) = batch_position(batch) else {
+                break;
+            };
 
-        // create pattern buffer
-        let pattern_buffer = Buffer::builder()
-            .queue(program_queue.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(config.pattern_len)
-            .copy_host_slice(&config.pattern[..])
-            .build()
-            .unwrap();
+            // the nonces of the salt prefix are used up, continue with the next one
+            if position.prefix != prefix {
+                prefix = position.prefix;
+                let random = keyspace.random(prefix);
+                lanes_buffer = build_lanes_buffer(&program_queue, &config, &random);
+                kernel.set_arg("lanes", &lanes_buffer).unwrap();
+                found_list.push(format!(
+                    "Nonces of salt prefix {} exhausted, continuing with 0x{}",
+                    prefix - 1,
+                    hex::encode(&random)
+                ));
+            }
 
-        // reset nonce & create a buffer to view it in little-endian
-        // for more uniformly distributed nonces, we shall initialize it to a random value
-        let mut nonce: [u32; 1] = rng.gen();
+            let mut slot = free
+                .pop()
+                .expect("a slot is free while the queue is not full");
+            slot.launch(&kernel, position, threshold);
+            in_flight.push_back(slot);
+            batch += 1;
+        }
 
-        let mut nonce_buffer = Buffer::builder()
-            .queue(program_queue.queue().clone())
-            .flags(MemFlags::new().read_only())
-            .len(1)
-            .copy_host_slice(&nonce)
-            .build()
-            .unwrap();
+        let Some(slot) = in_flight.pop_front() else {
+            found_list.push("Searched the whole keyspace of the salt template".to_string());
+            display.update(
+                launches as f64 * worksize as f64 / 1_000_000.0,
+                &config.pattern_label(),
+                &found_list,
+            );
+            return;
+        };
+
+        // block until the solutions of the oldest launch are read back
+        slot.read.wait_for().unwrap();
+        launches += 1;
+
+        // we don't want to print too fast
+        if previous_update.elapsed() >= Duration::from_secs(1) {
+            previous_update = Instant::now();
+
+            // the number of attempts made so far, in millions
+            let work_rate = launches as f64 * worksize as f64 / 1_000_000.0;
 
-        let mut solutions: Vec<u64> = vec![0; 1];
-        let solutions_buffer = Buffer::builder()
+            display.update(work_rate, &config.pattern_label(), &found_list);
+        }
+
+        if slot.found[1] != 0 {
+            let solution = slot.found[0];
+            let salt = config.salt.fill(&keyspace.random(slot.prefix), solution);
+
+            // recompute the address on the host, so a faulty device cannot report garbage
+            let address = compute_address(&config, &salt);
+
+            // verify the pattern, contains or expression match and score, the batches
+            // queued after this one may still report hits below a raised threshold
+            let score = config.ranking.score(&address);
+            let position = config
+                .contains
+                .as_ref()
+                .and_then(|nibbles| find_nibbles(nibbles, &address));
+            let contained = config.contains.is_none() || position.is_some();
+            let expression_position = config
+                .expression
+                .as_ref()
+                .and_then(|expression| expression.find(&address));
+            let expressed = config.expression.is_none() || expression_position.is_some();
+            if config.pattern.matches(&address) && contained && expressed && score >= threshold {
+                let mut output = format!(
+                    "0x{} => {} (Pattern: {})",
+                    hex::encode(salt),
+                    address,
+                    config.pattern_label(),
+                );
+                if let Some(position) = position {
+                    write!(output, " (Contains at nibble {})", position).unwrap();
+                }
+                if let Some(position) = expression_position {
+                    write!(output, " (Expression at nibble {})", position).unwrap();
+                }
+                if let Some(description) = config.ranking.describe(&address) {
+                    write!(output, " ({})", description).unwrap();
+                    threshold = score + 1;
+                }
+
+                found_list.push(output);
+            }
+        }
+
+        free.push(slot);
+    }
+}
+
+/// Length of the solutions buffer of a launch: the nonce of a hit, and a flag set along
+/// with it, as every nonce including 0 may be a hit.
+const SOLUTIONS_LEN: usize = 2;
+
+/// Number of kernel launches queued on the device at once.
+///
+/// The host blocks on the oldest launch while the others keep the device busy, and the
+/// bound keeps the queue from running far ahead of the verification of solutions.
+const MAX_IN_FLIGHT: usize = 3;
+
+/// Buffers of one queued kernel launch.
+struct LaunchSlot {
+    solutions: Buffer<u64>,
+    /// Host copy of `solutions`, written by a non-blocking read.
+    found: Vec<u64>,
+    /// Completion of the read into `found`.
+    read: Event,
+    /// Salt prefix of the launched batch, to rebuild the salt of a solution.
+    prefix: u64,
+}
+
+impl LaunchSlot {
+    fn new(program_queue: &ProQue) -> Self {
+        let solutions = Buffer::builder()
             .queue(program_queue.queue().clone())
             .flags(MemFlags::new().write_only())
-            .len(1)
-            .copy_host_slice(&solutions)
+            .len(SOLUTIONS_LEN)
+            .fill_val(0)
             .build()
             .unwrap();
 
-        // repeatedly enqueue kernel to search for new addresses
-        loop {
-            // build the kernel and define the type of each buffer
-            let kernel = program_queue
-                .kernel_builder("hashMessage")
-                .arg_named("message", None::<&Buffer<u8>>)
-                .arg_named("nonce", None::<&Buffer<u32>>)
-                .arg_named("pattern", None::<&Buffer<u8>>)
-                .arg_named("pattern_len", None::<&Buffer<u32>>)
-                .arg_named("solutions", None::<&Buffer<u64>>)
-                .build()
-                .unwrap();
-
-            // set each buffer
-            kernel.set_arg("message", Some(&salt_buffer)).unwrap();
-            kernel.set_arg("nonce", Some(&nonce_buffer)).unwrap();
-            kernel.set_arg("pattern", Some(&pattern_buffer)).unwrap();
-            kernel.set_arg("pattern_len", config.pattern_len as u32).unwrap();
-            kernel.set_arg("solutions", &solutions_buffer).unwrap();
-
-            // enqueue the kernel
-            unsafe {
-                kernel.enq().unwrap();
-            };
+        Self {
+            solutions,
+            found: vec![0; SOLUTIONS_LEN],
+            read: Event::empty(),
+            prefix: 0,
+        }
+    }
 
-            // calculate the current time
-            let mut now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            let current_time = now.as_secs();
+    /// Queue the batch at `position` followed by the read of its solutions, without
+    /// waiting for either.
+    fn launch(&mut self, kernel: &Kernel, position: SaltPosition, threshold: u32) {
+        self.prefix = position.prefix;
 
-            // we don't want to print too fast
-            let print_output = current_time - previous_time >= 1;
+        // clear a solution of the previous batch of this slot
+        self.solutions.cmd().fill(0, None).enq().unwrap();
 
-            // clear the terminal screen
-            if print_output {
-                previous_time = current_time;
+        kernel.set_arg("nonce", position.nonce).unwrap();
+        kernel.set_arg("threshold", threshold).unwrap();
+        kernel.set_arg("solutions", &self.solutions).unwrap();
+        unsafe {
+            kernel.enq().unwrap();
+        }
 
-                // determine the number of attempts being made per second
-                let work_rate: u128 = workfactor * cumulative_nonce as u128;
+        // `found` is only accessed again after waiting for `read`
+        unsafe {
+            self.solutions
+                .read(&mut self.found)
+                .block(false)
+                .enew(&mut self.read)
+                .enq()
+                .unwrap();
+        }
+    }
+}
 
-                display.update(work_rate, config.pattern_len, &found_list);
-            }
+/// Measure the throughput of the OpenCL kernel for `config` by repeatedly launching
+/// it for roughly `duration`. Returns the speed in million attempts per second.
+///
+/// With `specialized`, the pattern is compiled into the kernel as used by the miner,
+/// otherwise it is read from buffers like a generic kernel would.
+pub fn benchmark(
+    config: &MinerConfig,
+    duration: Duration,
+    specialized: bool,
+    options: &KernelOptions,
+) -> f64 {
+    let program_queue = build_program_queue(config, specialized, options);
+    let queue = program_queue.queue().clone();
+
+    let keyspace = Keyspace::new(config, rand::random(), 1, config.worksize);
+    let lanes_buffer = build_lanes_buffer(&program_queue, config, &keyspace.random(0));
+
+    let pattern_buffer = Buffer::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.value)
+        .build()
+        .unwrap();
 
-            // increment the cumulative nonce (does not reset after a match)
-            cumulative_nonce += 1;
+    let mask_buffer = Buffer::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.mask)
+        .build()
+        .unwrap();
 
-            // record the start time of the work
-            let work_start_time_millis = now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000;
+    let solutions_buffer = Buffer::<u64>::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().write_only())
+        .len(SOLUTIONS_LEN)
+        .fill_val(0)
+        .build()
+        .unwrap();
 
-            // sleep for 99% of the previous work duration to conserve CPU
-            if work_duration_millis != 0 {
-                std::thread::sleep(std::time::Duration::from_millis(
-                    work_duration_millis * 990 / 1000,
-                ));
-            }
+    let kernel = program_queue
+        .kernel_builder("hashMessage")
+        .arg(&lanes_buffer)
+        .arg(0u64)
+        .arg(&pattern_buffer)
+        .arg(&mask_buffer)
+        .arg(config.min_score)
+        .arg(&solutions_buffer)
+        .build()
+        .unwrap();
 
-            // read the solutions from the device
-            solutions_buffer.read(&mut solutions).enq().unwrap();
+    // the first launch includes driver warm-up, so it is excluded from the measurement
+    unsafe {
+        kernel.enq().unwrap();
+    }
+    queue.finish().unwrap();
 
-            // record the end time of the work and compute how long the work took
-            now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            work_duration_millis = (now.as_secs() * 1000 + now.subsec_nanos() as u64 / 1000000)
-                - work_start_time_millis;
+    let start = Instant::now();
+    let mut launches: u64 = 0;
+    while start.elapsed() < duration {
+        unsafe {
+            kernel.enq().unwrap();
+        }
+        queue.finish().unwrap();
+        launches += 1;
+    }
 
-            // if at least one solution is found, end the loop
-            if solutions[0] != 0 {
-                break;
-            }
+    (launches as f64 * config.worksize as f64) / start.elapsed().as_secs_f64() / 1_000_000.0
+}
 
-            // if no solution has yet been found, increment the nonce
-            nonce[0] += 1;
+/// How the OpenCL program of the miner is built.
+#[derive(Debug, Clone)]
+pub struct KernelOptions {
+    /// Reuse program binaries cached on disk instead of compiling from source.
+    pub cache: bool,
+    pub kernel: KeccakKernel,
+}
 
-            // update the nonce buffer with the incremented nonce value
-            nonce_buffer = Buffer::builder()
-                .queue(program_queue.queue().clone())
-                .flags(MemFlags::new().read_write())
-                .len(1)
-                .copy_host_slice(&nonce)
-                .build()
-                .unwrap();
-        }
+fn build_program_queue(config: &MinerConfig, specialized: bool, options: &KernelOptions) -> ProQue {
+    let platform = Platform::new(ocl::core::default_platform().unwrap());
+    let device = Device::by_idx_wrap(platform, 0).unwrap();
+    let context = Context::builder()
+        .platform(platform)
+        .devices(device)
+        .build()
+        .unwrap();
 
-        // iterate over each solution, first converting to a fixed array
-        for &solution in &solutions {
-            if solution == 0 {
-                continue;
-            }
+    let src = kernel_src(config, specialized, &options.kernel).unwrap();
+    let program = if options.cache {
+        kernel_cache::build_program(platform, device, &context, &src)
+    } else {
+        Program::builder()
+            .devices(device)
+            .src(src)
+            .build(&context)
+            .unwrap()
+    };
 
-            let solution = solution.to_le_bytes();
+    let queue = Queue::new(&context, device, None).unwrap();
+    let program_queue = ProQue::new(context, queue, program, Some(config.worksize));
+
+    // a kernel computing wrong hashes would never find anything, or report garbage
+    if let Err(err) = check_permutations(&program_queue) {
+        eprintln!(
+            "The '{}' kernel failed its known-answer test: {}",
+            options.kernel.name, err
+        );
+        process::exit(1);
+    }
 
-            let mut solution_message = [0; 85];
-            solution_message[0] = CONTROL_CHARACTER;
-            solution_message[1..21].copy_from_slice(&config.factory);
-            solution_message[21..41].copy_from_slice(&config.caller);
-            solution_message[41..45].copy_from_slice(&salt[..]);
-            solution_message[45..53].copy_from_slice(&solution);
-            solution_message[53..].copy_from_slice(&config.codehash);
+    program_queue
+}
 
-            // create new hash object
-            let mut hash = Keccak256::new();
+/// Run both Keccak-f[1600] permutations of the program on fixed states and compare the
+/// results with the host's.
+fn check_permutations(program_queue: &ProQue) -> Result<(), String> {
+    // the padded empty message, whose digest is keccak256(""), and an arbitrary state
+    let mut expected = [
+        padded_block(&[]),
+        std::array::from_fn(|i| 0x0123456789abcdefu64.rotate_left(7 * i as u32) ^ i as u64),
+    ];
+    let mut states = expected.concat();
+
+    let buffer = Buffer::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().read_write())
+        .len(states.len())
+        .copy_host_slice(&states)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let kernel = program_queue
+        .kernel_builder("keccakSelfTest")
+        .global_work_size(1)
+        .arg(&buffer)
+        .build()
+        .map_err(|err| err.to_string())?;
+    unsafe {
+        kernel.enq().map_err(|err| err.to_string())?;
+    }
+    buffer
+        .read(&mut states)
+        .enq()
+        .map_err(|err| err.to_string())?;
+
+    expected.iter_mut().for_each(keccak_f);
+
+    // keccakf only has to compute the address, the upper half of lane 1 and lanes 2, 3
+    let address = |state: &[u64]| (state[1] >> 32, state[2], state[3]);
+    if address(&states[..25]) != address(&expected[0]) {
+        return Err(format!(
+            "keccakf hashed the empty message to {}, expected {}",
+            hex::encode(lanes_bytes(&states[..4])),
+            hex::encode(lanes_bytes(&expected[0][..4]))
+        ));
+    }
+    if states[25..] != expected[1] {
+        return Err("keccakfFull does not match Keccak-f[1600]".to_string());
+    }
 
-            // update with header
-            hash.update(&solution_message);
+    Ok(())
+}
 
-            // hash the payload and get the result
-            let mut res: [u8; 32] = [0; 32];
-            hash.finalize_into(&mut res);
+/// Little-endian bytes of `lanes`.
+fn lanes_bytes(lanes: &[u64]) -> Vec<u8> {
+    lanes.iter().flat_map(|lane| lane.to_le_bytes()).collect()
+}
 
-            // get the address that results from the hash
-            let address = <&Address>::try_from(&res[12..]).unwrap();
+/// Number of salts per range hashed by the device self-test.
+const SELF_TEST_SALTS: usize = 256;
 
-            // verify the pattern match
-            let mut matches = true;
-            for i in 0..config.pattern_len {
-                if address[i] != config.pattern[i] {
-                    matches = false;
-                    break;
-                }
-            }
+/// Check the program of the run against the host before mining, so a miscompiled
+/// kernel or a faulty device cannot silently miss hits or report garbage.
+///
+/// The addresses the device computes for two ranges of salts with the random segment
+/// `random`, the first one of the run and one crossing the 32-bit boundary of the
+/// nonce, have to be the ones [`compute_address`] gives, and the device has to agree
+/// with [`is_hit`] on which of them are hits, as does the solution of `hashMessage`.
+/// The addresses of [`hit_candidates`] force a hit through the constraints compiled
+/// into the program.
+fn self_test(program_queue: &ProQue, config: &MinerConfig, random: &[u8]) -> Result<(), String> {
+    let err = |err: ocl::Error| err.to_string();
+    let queue = program_queue.queue();
+    let lanes_buffer = build_lanes_buffer(program_queue, config, random);
+    let pattern_buffer = Buffer::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.value)
+        .build()
+        .map_err(err)?;
+    let mask_buffer = Buffer::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().read_only())
+        .len(20)
+        .copy_host_slice(&config.pattern.mask)
+        .build()
+        .map_err(err)?;
 
-            if matches {
-                let output = format!(
-                    "0x{}{}{} => {} (Pattern: {})",
-                    hex::encode(config.caller),
+    for first in [0, (1 << 32) - SELF_TEST_SALTS as u64 / 2] {
+        // the address lanes of every salt followed by whether it is a hit
+        let mut results = vec![0u64; 4 * SELF_TEST_SALTS];
+        let results_buffer = Buffer::<u64>::builder()
+            .queue(queue.clone())
+            .flags(MemFlags::new().write_only())
+            .len(results.len())
+            .build()
+            .map_err(err)?;
+        let kernel = program_queue
+            .kernel_builder("hashSelfTest")
+            .global_work_size(SELF_TEST_SALTS)
+            .arg(&lanes_buffer)
+            .arg(first)
+            .arg(&pattern_buffer)
+            .arg(&mask_buffer)
+            .arg(config.min_score)
+            .arg(&results_buffer)
+            .build()
+            .map_err(err)?;
+        unsafe {
+            kernel.enq().map_err(err)?;
+        }
+        results_buffer.read(&mut results).enq().map_err(err)?;
+
+        let mut hits = vec![];
+        for (nonce, result) in (first..).zip(results.chunks(4)) {
+            let salt = config.salt.fill(random, nonce);
+            let expected = compute_address(config, &salt);
+            let address = address_of_lanes(&result[..3]);
+            if address != expected {
+                return Err(format!(
+                    "the kernel hashed the salt 0x{} to {}, expected {}",
                     hex::encode(salt),
-                    hex::encode(solution),
                     address,
-                    hex::encode(&config.pattern),
-                );
+                    expected
+                ));
+            }
 
-                found_list.push(output);
+            let hit = is_hit(config, &address);
+            if (result[3] != 0) != hit {
+                return Err(format!(
+                    "the kernel {} the address {}",
+                    if hit { "missed the hit" } else { "reported" },
+                    address
+                ));
+            }
+            if hit {
+                hits.push(nonce);
+            }
+        }
+
+        let pattern = [&pattern_buffer, &mask_buffer];
+        match search(
+            program_queue,
+            &lanes_buffer,
+            pattern,
+            config.min_score,
+            first,
+        )? {
+            Some(nonce) if !hits.contains(&nonce) => {
+                return Err(format!(
+                    "hashMessage reported nonce {}, which is not a hit",
+                    nonce
+                ));
+            }
+            None if !hits.is_empty() => {
+                return Err(format!(
+                    "hashMessage reported no hit, expected one of the nonces {:?}",
+                    hits
+                ));
             }
+            _ => {}
         }
     }
-}
 
-fn mk_kernel_src(config: &MinerConfig) -> String {
-    let mut src = String::with_capacity(2048 + KERNEL_SRC.len());
+    let candidates = hit_candidates(config);
+    let addresses: Vec<u64> = candidates.iter().flat_map(address_lanes).collect();
+    let addresses_buffer = Buffer::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().read_only())
+        .len(addresses.len())
+        .copy_host_slice(&addresses)
+        .build()
+        .map_err(err)?;
+    let mut results = vec![0u64; candidates.len()];
+    let results_buffer = Buffer::<u64>::builder()
+        .queue(queue.clone())
+        .flags(MemFlags::new().write_only())
+        .len(results.len())
+        .build()
+        .map_err(err)?;
+    let kernel = program_queue
+        .kernel_builder("matchSelfTest")
+        .global_work_size(candidates.len())
+        .arg(&addresses_buffer)
+        .arg(&pattern_buffer)
+        .arg(&mask_buffer)
+        .arg(config.min_score)
+        .arg(&results_buffer)
+        .build()
+        .map_err(err)?;
+    unsafe {
+        kernel.enq().map_err(err)?;
+    }
+    results_buffer.read(&mut results).enq().map_err(err)?;
+
+    for (address, result) in candidates.iter().zip(results) {
+        let hit = is_hit(config, address);
+        if (result != 0) != hit {
+            return Err(format!(
+                "the kernel {} the address {}",
+                if hit { "missed the hit" } else { "reported" },
+                address
+            ));
+        }
+    }
 
-    let factory = config.factory.iter();
-    let caller = config.caller.iter();
-    let hash = config.codehash.iter();
-    let hash = hash.enumerate().map(|(i, x)| (i + 52, x));
+    Ok(())
+}
 
-    for (i, x) in factory.chain(caller).enumerate().chain(hash) {
-        writeln!(src, "#define S_{} {}u", i + 1, x).unwrap();
+/// Launch `hashMessage` for the [`SELF_TEST_SALTS`] salts from `nonce`, with the pattern
+/// and mask buffers `pattern`, returning the solution of the kernel if it reported a hit.
+fn search(
+    program_queue: &ProQue,
+    lanes_buffer: &Buffer<u64>,
+    pattern: [&Buffer<u8>; 2],
+    threshold: u32,
+    nonce: u64,
+) -> Result<Option<u64>, String> {
+    let solutions_buffer = Buffer::<u64>::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().write_only())
+        .len(SOLUTIONS_LEN)
+        .fill_val(0)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let kernel = program_queue
+        .kernel_builder("hashMessage")
+        .global_work_size(SELF_TEST_SALTS)
+        .arg(lanes_buffer)
+        .arg(nonce)
+        .arg(pattern[0])
+        .arg(pattern[1])
+        .arg(threshold)
+        .arg(&solutions_buffer)
+        .build()
+        .map_err(|err| err.to_string())?;
+    unsafe {
+        kernel.enq().map_err(|err| err.to_string())?;
     }
 
-    src.push_str(KERNEL_SRC);
+    let mut solutions = vec![0u64; SOLUTIONS_LEN];
+    solutions_buffer
+        .read(&mut solutions)
+        .enq()
+        .map_err(|err| err.to_string())?;
+    Ok((solutions[1] != 0).then_some(solutions[0]))
+}
+
+/// The address as held by the kernel, in the upper half of lane 1 and lanes 2 and 3.
+fn address_lanes(address: &Address) -> [u64; 3] {
+    let mut bytes = [0u8; 32];
+    bytes[12..].copy_from_slice(address.as_slice());
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[8 * (i + 1)..][..8].try_into().unwrap()))
+}
+
+/// The address held in lanes 1 to 3, see [`address_lanes`].
+fn address_of_lanes(lanes: &[u64]) -> Address {
+    Address::from_slice(&lanes_bytes(lanes)[4..])
+}
+
+/// Upload the message lanes of `config` for the salt prefix with random segment `random`.
+fn build_lanes_buffer(program_queue: &ProQue, config: &MinerConfig, random: &[u8]) -> Buffer<u64> {
+    let lanes = MessageLanes::new(config, random).to_vec();
+    Buffer::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(lanes.len())
+        .copy_host_slice(&lanes)
+        .build()
+        .unwrap()
+}
 
-    return src;
+/// Check that `config` can be mined by the OpenCL kernel, i.e. that a pattern
+/// expression can be lowered to OpenCL C.
+pub fn check_kernel_support(config: &MinerConfig) -> Result<(), String> {
+    kernel_src(config, true, &KeccakKernel::default()).map(|_| ())
 }
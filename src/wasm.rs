@@ -1,4 +1,6 @@
-use crate::core::{parse_config, run_batch, FoundResult, MinerConfig, RawConfig};
+use crate::core::{
+    keyspace::Keyspace, parse_config, run_batch, self_test, FoundResult, MinerConfig, RawConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
@@ -12,8 +14,13 @@ struct BatchResult {
 #[derive(Debug)]
 struct WorkerState {
     config: Option<MinerConfig>,
+    /// Seed of the run, shared by all workers.
     seed: u64,
     worker_id: u32,
+    /// Number of workers of the run.
+    workers: u32,
+    /// Index of the next batch of this worker.
+    batch: u64,
     stop: bool,
 }
 
@@ -22,21 +29,37 @@ thread_local! {
         config: None,
         seed: 0,
         worker_id: 0,
+        workers: 1,
+        batch: 0,
         stop: false,
     });
 }
 
 #[wasm_bindgen]
-pub fn init_worker(config: JsValue, seed: u32, worker_id: u32) -> Result<(), JsValue> {
+pub fn init_worker(
+    config: JsValue,
+    seed: u32,
+    worker_id: u32,
+    workers: u32,
+) -> Result<(), JsValue> {
+    if worker_id >= workers {
+        return Err(JsValue::from_str("Worker id out of range"));
+    }
     let raw: RawConfig = serde_wasm_bindgen::from_value(config)
         .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
     let parsed = parse_config(raw).map_err(|e| JsValue::from_str(&e))?;
 
+    // a broken build would silently miss hits or report wrong salts
+    self_test::check_cpu()
+        .map_err(|e| JsValue::from_str(&format!("Self-test failed, not mining: {}.", e)))?;
+
     STATE.with(|state| {
         let mut state = state.borrow_mut();
         state.config = Some(parsed);
         state.seed = seed as u64;
         state.worker_id = worker_id;
+        state.workers = workers;
+        state.batch = 0;
         state.stop = false;
     });
 
@@ -66,8 +89,15 @@ pub fn run_batch_wasm(batch_size: u32) -> Result<JsValue, JsValue> {
             .as_ref()
             .ok_or_else(|| JsValue::from_str("Worker not initialized"))?;
 
-        let (found, attempts) = run_batch(config, state.seed, state.worker_id, batch_size);
-        state.seed = state.seed.wrapping_add(1);
+        // the batch size stays the same during a run, so batches never overlap
+        let keyspace = Keyspace::new(config, state.seed, state.workers, batch_size);
+        let (found, attempts) = run_batch(config, &keyspace, state.worker_id, state.batch);
+        if attempts == 0 {
+            return Err(JsValue::from_str(
+                "Searched the whole keyspace of the salt template",
+            ));
+        }
+        state.batch += 1;
 
         Ok(BatchResult { found, attempts })
     })?;
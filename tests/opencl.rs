@@ -0,0 +1,273 @@
+//! Runs the `hashMessage` kernel on an OpenCL device, preferably a CPU runtime such as
+//! POCL, and cross-checks every solution it reports with the CPU hashing of
+//! `salty::core`. Without an installed OpenCL platform the device checks are skipped.
+#![cfg(feature = "cli")]
+
+use ocl::{flags::DeviceType, Buffer, Context, Device, MemFlags, Platform, ProQue, Program, Queue};
+use salty::core::{
+    compute_address,
+    kernel::{kernel_src, KeccakKernel, KERNELS},
+    lanes::MessageLanes,
+    parse_config,
+    pattern::Pattern,
+    self_test::{is_hit, known_config, KNOWN_ADDRESS, KNOWN_NONCE},
+    MinerConfig, RawConfig,
+};
+
+/// Salts searched by a launch.
+const WORKSIZE: u64 = 256;
+
+/// First nonces of the launches of a run, including launches crossing the 32-bit
+/// boundary of the nonce and ending at the last nonce.
+fn launches() -> Vec<u64> {
+    let mut launches: Vec<u64> = (0..16).map(|launch| launch * WORKSIZE).collect();
+    launches.push((1 << 32) - WORKSIZE / 2);
+    launches.push(u64::MAX - (WORKSIZE - 1));
+    launches
+}
+
+fn config(pattern: &str, configure: impl FnOnce(&mut RawConfig)) -> MinerConfig {
+    let mut raw = RawConfig {
+        factory: "0x4e1DCf7AD4e460CfD30791CCC4F9c8a4f820ec67".to_string(),
+        caller: Some("0x0000000000FFe8B47B3e2130213B802212439497".to_string()),
+        codehash: Some(format!("0x{}", "11".repeat(32))),
+        worksize: WORKSIZE as u32,
+        pattern: pattern.to_string(),
+        ..Default::default()
+    };
+    configure(&mut raw);
+    parse_config(raw).unwrap()
+}
+
+/// The first OpenCL device, preferring a CPU device, `None` without an OpenCL platform.
+fn device() -> Option<(Platform, Device)> {
+    let platforms: Vec<Platform> = ocl::core::get_platform_ids()
+        .ok()?
+        .into_iter()
+        .map(Platform::new)
+        .collect();
+
+    [Some(DeviceType::CPU), None]
+        .into_iter()
+        .find_map(|device_type| {
+            platforms.iter().find_map(|&platform| {
+                let devices = Device::list(platform, device_type).ok()?;
+                devices.first().map(|&device| (platform, device))
+            })
+        })
+}
+
+fn build_program_queue(platform: Platform, device: Device, src: String) -> ProQue {
+    let context = Context::builder()
+        .platform(platform)
+        .devices(device)
+        .build()
+        .unwrap();
+    let program = Program::builder()
+        .devices(device)
+        .src(src)
+        .build(&context)
+        .unwrap();
+    let queue = Queue::new(&context, device, None).unwrap();
+    ProQue::new(context, queue, program, Some(WORKSIZE as usize))
+}
+
+/// Launch `hashMessage` for the `WORKSIZE` salts from `nonce`, returning its solution if
+/// it reported a hit.
+fn search(program_queue: &ProQue, config: &MinerConfig, random: &[u8], nonce: u64) -> Option<u64> {
+    let lanes = MessageLanes::new(config, random).to_vec();
+    let lanes_buffer = Buffer::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().read_only())
+        .len(lanes.len())
+        .copy_host_slice(&lanes)
+        .build()
+        .unwrap();
+    let buffer = |bytes: &[u8; 20]| {
+        Buffer::builder()
+            .queue(program_queue.queue().clone())
+            .flags(MemFlags::new().read_only())
+            .len(20)
+            .copy_host_slice(bytes)
+            .build()
+            .unwrap()
+    };
+    let pattern_buffer = buffer(&config.pattern.value);
+    let mask_buffer = buffer(&config.pattern.mask);
+    let solutions_buffer = Buffer::<u64>::builder()
+        .queue(program_queue.queue().clone())
+        .flags(MemFlags::new().write_only())
+        .len(2)
+        .fill_val(0)
+        .build()
+        .unwrap();
+
+    let kernel = program_queue
+        .kernel_builder("hashMessage")
+        .arg(&lanes_buffer)
+        .arg(nonce)
+        .arg(&pattern_buffer)
+        .arg(&mask_buffer)
+        .arg(config.min_score)
+        .arg(&solutions_buffer)
+        .build()
+        .unwrap();
+    unsafe {
+        kernel.enq().unwrap();
+    }
+
+    // the nonce of a hit and the flag set along with it
+    let mut solutions = vec![0u64; 2];
+    solutions_buffer.read(&mut solutions).enq().unwrap();
+    (solutions[1] != 0).then_some(solutions[0])
+}
+
+/// Run every bundled kernel, generic and specialized, over the launches of [`launches`]
+/// and check each solution against the hits among the salts of its launch.
+fn cross_check(config: MinerConfig) {
+    let random: Vec<u8> = (1..=config.salt.random_len() as u8).collect();
+
+    // the hits of every launch, which the pattern has to make likely enough to test
+    let hits: Vec<(u64, Vec<u64>)> = launches()
+        .into_iter()
+        .map(|first| {
+            let nonces = (first..=first + (WORKSIZE - 1)).filter(|&nonce| {
+                let salt = config.salt.fill(&random, nonce);
+                is_hit(&config, &compute_address(&config, &salt))
+            });
+            (first, nonces.collect())
+        })
+        .collect();
+    assert!(
+        hits.iter().any(|(_, nonces)| !nonces.is_empty()),
+        "no launch of {} has a hit",
+        config.pattern_label()
+    );
+
+    let Some((platform, device)) = device() else {
+        eprintln!("No OpenCL device found, skipping the kernel.");
+        return;
+    };
+
+    for &(name, _) in KERNELS {
+        let kernel = KeccakKernel::load(name).unwrap();
+        for specialized in [false, true] {
+            let src = kernel_src(&config, specialized, &kernel).unwrap();
+            let program_queue = build_program_queue(platform, device, src);
+
+            for (first, nonces) in &hits {
+                let solution = search(&program_queue, &config, &random, *first);
+                let context = format!(
+                    "{} kernel ({}), {} from nonce {}",
+                    name,
+                    if specialized {
+                        "specialized"
+                    } else {
+                        "generic"
+                    },
+                    config.pattern_label(),
+                    first
+                );
+                match solution {
+                    Some(nonce) => assert!(
+                        nonces.contains(&nonce),
+                        "{}: reported nonce {}, expected one of {:?}",
+                        context,
+                        nonce,
+                        nonces
+                    ),
+                    None => assert!(
+                        nonces.is_empty(),
+                        "{}: reported no hit, expected one of {:?}",
+                        context,
+                        nonces
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn create2_prefix() {
+    cross_check(config("00", |_| {}));
+}
+
+#[test]
+fn salt_template() {
+    cross_check(config("a5", |raw| {
+        raw.salt = Some("0xcafe,random:10,nonce,zero:12".to_string());
+    }));
+}
+
+#[test]
+fn safe_proxy() {
+    cross_check(config("00", |raw| {
+        raw.derivation = Some("safe".to_string());
+        raw.singleton = Some("0x41675C099F32341bf84BFc5382aF534df5C7461a".to_string());
+        raw.initializer = Some("0xb63e800d".to_string());
+        raw.proxy_code = Some("0x6080604052".to_string());
+    }));
+}
+
+#[test]
+fn zksync() {
+    cross_check(config("00", |raw| raw.chain = Some("zksync".to_string())));
+}
+
+#[test]
+fn contains() {
+    cross_check(config("bee", |raw| {
+        raw.match_mode = Some("contains".to_string());
+    }));
+}
+
+#[test]
+fn expression() {
+    cross_check(config("^(.)\\1", |raw| {
+        raw.match_mode = Some("expression".to_string());
+    }));
+}
+
+#[test]
+fn zero_bytes_ranking() {
+    cross_check(config("", |raw| {
+        raw.ranking = Some("zero-bytes".to_string());
+        raw.min_score = Some(2);
+    }));
+}
+
+#[test]
+fn known_answer() {
+    // the EIP-1014 example, and the first salt of the template, which the kernel has to
+    // report although its nonce is 0
+    let first_salt = known_config(Pattern::any()).salt.fill(&[], 0);
+    let first_address = compute_address(&known_config(Pattern::any()), &first_salt);
+    let targets = [
+        (KNOWN_NONCE, KNOWN_ADDRESS, KNOWN_NONCE - WORKSIZE / 2),
+        (0, first_address, 0),
+    ];
+
+    let Some((platform, device)) = device() else {
+        eprintln!("No OpenCL device found, skipping the kernel.");
+        return;
+    };
+
+    for (nonce, address, first) in targets {
+        let config = known_config(Pattern::prefix(address.as_slice()));
+        for &(name, _) in KERNELS {
+            let kernel = KeccakKernel::load(name).unwrap();
+            for specialized in [false, true] {
+                let src = kernel_src(&config, specialized, &kernel).unwrap();
+                let program_queue = build_program_queue(platform, device, src);
+                assert_eq!(
+                    search(&program_queue, &config, &[], first),
+                    Some(nonce),
+                    "{} kernel, specialized: {}",
+                    name,
+                    specialized
+                );
+            }
+        }
+    }
+}
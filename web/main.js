@@ -68,10 +68,11 @@ function buildConfig() {
 
   return {
     factory: form.elements.factory.value.trim(),
-    caller: form.elements.caller.value.trim(),
+    caller: form.elements.caller.value.trim() || undefined,
     codehash: form.elements.codehash.value.trim(),
     pattern,
     worksize: Number.isFinite(worksize) ? worksize : DEFAULTS.worksize,
+    salt: form.elements.salt.value.trim() || undefined,
   };
 }
 
@@ -128,10 +129,14 @@ function stopMining() {
 function validateConfig(config) {
   const checks = [
     ["factory", config.factory, 40],
-    ["caller", config.caller, 40],
     ["codehash", config.codehash, 64],
   ];
 
+  // the caller may be left empty for permissionless salt layouts (e.g. zero-caller)
+  if (config.caller) {
+    checks.push(["caller", config.caller, 40]);
+  }
+
   for (const [name, value, length] of checks) {
     const hex = strip0x(value);
     if (hex.length !== length) {
@@ -215,6 +220,10 @@ function startMining() {
 
   updateStatus(0);
 
+  // every worker searches its own part of the keyspace of this seed
+  const seed = crypto.getRandomValues(new Uint32Array(1))[0];
+  appendLog(`Seed: ${seed}`);
+
   for (let i = 0; i < workerCount; i += 1) {
     const worker = new Worker(new URL("./worker.js", import.meta.url), {
       type: "module",
@@ -227,6 +236,13 @@ function startMining() {
         return;
       }
 
+      if (type === "build") {
+        if (workerId === 0) {
+          appendLog(event.data.simd ? "Using the SIMD build." : "Using the scalar build.");
+        }
+        return;
+      }
+
       if (type === "found") {
         results.forEach((result) => {
           appendLog(
@@ -247,7 +263,6 @@ function startMining() {
       }
     };
 
-    const seed = (Date.now() + i * 9973) >>> 0;
     worker.postMessage({
       type: "start",
       payload: {
@@ -255,6 +270,7 @@ function startMining() {
         seed,
         batchSize,
         workerId: i,
+        workerCount,
         stopFlagBuffer,
         shared: supportsShared,
       },
@@ -1,4 +1,14 @@
-import init, { init_worker, run_batch_wasm, set_stop } from "./pkg/salty.js";
+// (func (result v128) i32.const 0 i8x16.splat i8x16.popcnt), which only validates
+// in engines supporting WebAssembly SIMD
+const SIMD_PROBE = new Uint8Array([
+  0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0,
+  65, 0, 253, 15, 253, 98, 11,
+]);
+
+let init_worker;
+let run_batch_wasm;
+let set_stop;
+let simd = false;
 
 let ready = false;
 let running = false;
@@ -8,9 +18,27 @@ let lastTick = 0;
 let totalAttempts = 0;
 let stopFlag = null;
 
+// load the simd128 build where supported, falling back to the scalar build
+async function loadBuild() {
+  if (WebAssembly.validate(SIMD_PROBE)) {
+    try {
+      const pkg = await import("./pkg-simd/salty.js");
+      await pkg.default();
+      simd = true;
+      return pkg;
+    } catch (error) {
+      console.warn("SIMD build unavailable, using the scalar build.", error);
+    }
+  }
+
+  const pkg = await import("./pkg/salty.js");
+  await pkg.default();
+  return pkg;
+}
+
 async function ensureReady() {
   if (!ready) {
-    await init();
+    ({ init_worker, run_batch_wasm, set_stop } = await loadBuild());
     ready = true;
   }
 }
@@ -73,8 +101,9 @@ self.onmessage = async (event) => {
     totalAttempts = 0;
     lastTick = performance.now();
     try {
-      init_worker(payload.config, payload.seed, workerId);
+      init_worker(payload.config, payload.seed, workerId, payload.workerCount);
       set_stop(false);
+      postMessage({ type: "build", simd, workerId });
       tick();
     } catch (error) {
       postMessage({ type: "error", message: String(error) });